@@ -177,13 +177,28 @@ func (b *Bash) Complete(input string) (int, []string, error) {
 	//   "ls ./b" => "bar", "baz"
 	//   "ls ./*" => "foo", "bar", "baz"
 	//   "ls --c" => "--classify", "--color=", ...
-	// Current logic: back up until we hit a space or a slash.
+	// Current logic: back up until we hit an unescaped space or a slash.
+	//   "ls a\ b" => "a b"
+	// A space is escaped if preceded by an odd number of backslashes:
+	//   "ls a\\ b" => "b"
 	var ofs int
 	for ofs = len(input); ofs > 0; ofs-- {
-		if input[ofs-1] == ' ' || input[ofs-1] == '/' {
+		if input[ofs-1] == ' ' && !escaped(input, ofs-1) {
+			break
+		}
+		if input[ofs-1] == '/' {
 			break
 		}
 	}
 
 	return ofs, expansions, err
 }
+
+// escaped returns whether the character at input[i] is backslash-escaped.
+func escaped(input string, i int) bool {
+	n := 0
+	for ; i > 0 && input[i-1] == '\\'; i-- {
+		n++
+	}
+	return n%2 == 1
+}
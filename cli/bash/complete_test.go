@@ -0,0 +1,19 @@
+package bash
+
+import "testing"
+
+func TestEscaped(t *testing.T) {
+	for _, test := range []struct {
+		input string
+		want  bool
+	}{
+		{`a b`, false},
+		{`a\ b`, true},
+		{`a\\ b`, false},
+		{`a\\\ b`, true},
+	} {
+		if got := escaped(test.input, len(test.input)-2); got != test.want {
+			t.Errorf("escaped(%q) = %v, want %v", test.input, got, test.want)
+		}
+	}
+}
@@ -0,0 +1,50 @@
+package main
+
+import (
+	"io/ioutil"
+	"path/filepath"
+	"strings"
+)
+
+// gitBranch returns the git branch checked out in the repository containing
+// dir, the abbreviated commit if HEAD is detached, or "" if dir isn't in a
+// repository.  It reads .git/HEAD directly, as running git for every prompt
+// would be slow.
+func gitBranch(dir string) string {
+	for {
+		head, ok := readGitHead(filepath.Join(dir, ".git"))
+		if ok {
+			if ref := strings.TrimPrefix(head, "ref: "); ref != head {
+				return strings.TrimPrefix(ref, "refs/heads/")
+			}
+			if len(head) > 7 {
+				head = head[:7]
+			}
+			return head
+		}
+		parent := filepath.Dir(dir)
+		if parent == dir {
+			return ""
+		}
+		dir = parent
+	}
+}
+
+// readGitHead reads the HEAD file of a .git directory, following the
+// "gitdir: PATH" file that worktrees and submodules have in its place.
+func readGitHead(git string) (string, bool) {
+	buf, err := ioutil.ReadFile(git)
+	if err == nil {
+		// A file, not a directory.
+		gitdir := strings.TrimPrefix(strings.TrimSpace(string(buf)), "gitdir: ")
+		if !filepath.IsAbs(gitdir) {
+			gitdir = filepath.Join(filepath.Dir(git), gitdir)
+		}
+		git = gitdir
+	}
+	buf, err = ioutil.ReadFile(filepath.Join(git, "HEAD"))
+	if err != nil {
+		return "", false
+	}
+	return strings.TrimSpace(string(buf)), true
+}
@@ -0,0 +1,76 @@
+package main
+
+import (
+	"fmt"
+	"os"
+	"sort"
+	"strings"
+)
+
+// Shell integration scripts, for running a real shell inside a smash cell.
+// They report the shell's working directory with OSC 7 at each prompt, which
+// smash adopts as the session cwd, so smash's next prompt after the shell
+// exits is in the directory the shell was last in.
+//
+// Usage, e.g. in ~/.bashrc:
+//   [ -n "$SMASH" ] && eval "$("$SMASH" print-integration bash)"
+var integrationScripts = map[string]string{
+	"bash": `__smash_urlencode() {
+  local LC_ALL=C s="$1" out= c i
+  for (( i = 0; i < ${#s}; i++ )); do
+    c=${s:i:1}
+    case $c in
+      [-/._~A-Za-z0-9]) out+=$c ;;
+      *) printf -v c '%%%02X' "'$c"; out+=$c ;;
+    esac
+  done
+  printf '%s' "$out"
+}
+__smash_osc7() {
+  printf '\e]7;file://%s%s\a' "$HOSTNAME" "$(__smash_urlencode "$PWD")"
+}
+PROMPT_COMMAND="__smash_osc7${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+`,
+	"zsh": `__smash_urlencode() {
+  local LC_ALL=C s="$1" out= c i
+  for (( i = 0; i < ${#s}; i++ )); do
+    c=${s:$i:1}
+    case $c in
+      [-/._~A-Za-z0-9]) out+=$c ;;
+      *) printf -v c '%%%02X' "'$c"; out+=$c ;;
+    esac
+  done
+  printf '%s' "$out"
+}
+__smash_osc7() {
+  printf '\e]7;file://%s%s\a' "$HOST" "$(__smash_urlencode "$PWD")"
+}
+autoload -Uz add-zsh-hook
+add-zsh-hook precmd __smash_osc7
+`,
+	"fish": `function __smash_osc7 --on-event fish_prompt
+    printf '\e]7;file://%s%s\a' $hostname (string escape --style=url -- $PWD)
+end
+`,
+}
+
+// printIntegration implements "smash print-integration SHELL".
+func printIntegration(args []string) error {
+	var shells []string
+	for shell := range integrationScripts {
+		shells = append(shells, shell)
+	}
+	sort.Strings(shells)
+	usage := fmt.Errorf("usage: smash print-integration %s",
+		strings.Join(shells, "|"))
+
+	if len(args) != 1 {
+		return usage
+	}
+	script, ok := integrationScripts[args[0]]
+	if !ok {
+		return usage
+	}
+	_, err := os.Stdout.WriteString(script)
+	return err
+}
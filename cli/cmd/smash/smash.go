@@ -5,14 +5,18 @@ import (
 	"bytes"
 	"fmt"
 	"io"
+	"io/ioutil"
 	"log"
 	"net"
 	"net/http"
 	"os"
 	"os/exec"
 	"path/filepath"
+	"regexp"
+	"runtime"
 	"strings"
 	"sync"
+	"sync/atomic"
 	"syscall"
 	"time"
 
@@ -73,18 +77,27 @@ type command struct {
 
 	// stdin accepts input keys and forwards them to the subprocess.
 	stdin chan []byte
+
+	// timedOut is set (atomically, to 1) when the subprocess is killed for
+	// running past req.TimeoutMs.
+	timedOut int32
+
+	// dir is the directory the command finished in: req.Cwd, unless it
+	// changed directory via cd or reported one with OSC 7.
+	dir string
 }
 
 func newCmd(conn *conn, req *proto.RunRequest) *command {
 	cmd := &exec.Cmd{Path: req.Argv[0], Args: req.Argv}
-	// TODO: accept environment from the client
-	cmd.Env = os.Environ()
-	cmd.Env = append(cmd.Env, "SMASH_SOCK="+globalSockPathForEnv)
+	for _, pair := range req.Env {
+		cmd.Env = append(cmd.Env, pair.Key+"="+pair.Val)
+	}
 	cmd.Dir = req.Cwd
 	return &command{
 		conn: conn,
 		req:  req,
 		cmd:  cmd,
+		dir:  req.Cwd,
 	}
 }
 
@@ -117,18 +130,7 @@ func termLoop(tr *vt100.TermReader, r io.Reader) error {
 // the subprocess exit code.
 func (cmd *command) run() (int, error) {
 	if cmd.cmd.Path == "cd" {
-		if len(cmd.cmd.Args) != 2 {
-			return 0, fmt.Errorf("bad arguments to cd")
-		}
-		dir := cmd.cmd.Args[1]
-		st, err := os.Stat(dir)
-		if err != nil {
-			return 0, err
-		}
-		if !st.IsDir() {
-			return 0, fmt.Errorf("%s: not a directory", dir)
-		}
-		return 0, nil
+		return cmd.chdir(cmd.cmd.Args[1:])
 	}
 
 	if filepath.Base(cmd.cmd.Path) == cmd.cmd.Path {
@@ -149,6 +151,19 @@ func (cmd *command) run() (int, error) {
 		return 0, err
 	}
 
+	if cmd.req.TimeoutMs > 0 {
+		// The pty runs the subprocess in a new session, so its pid is also
+		// its process group id; killing the group gets any children too.
+		// SIGKILL, as a hung command may well ignore gentler signals.
+		pid := cmd.cmd.Process.Pid
+		timeout := time.Duration(cmd.req.TimeoutMs) * time.Millisecond
+		timer := time.AfterFunc(timeout, func() {
+			atomic.StoreInt32(&cmd.timedOut, 1)
+			syscall.Kill(-pid, syscall.SIGKILL)
+		})
+		defer timer.Stop()
+	}
+
 	cmd.stdin = make(chan []byte)
 	go func() {
 		for input := range cmd.stdin {
@@ -162,6 +177,9 @@ func (cmd *command) run() (int, error) {
 	drawPending := false
 	var done error
 
+	// The OSC 7 working directory last reported to the client.
+	cwd := ""
+
 	var tr *vt100.TermReader
 	renderFromDirty := func() {
 		// Called with mu held.
@@ -199,11 +217,22 @@ func (cmd *command) run() (int, error) {
 			update.Rows = append(update.Rows, rowSpans)
 		}
 		update.RowCount = len(term.Lines)
+		update.AltScreen = !term.CanScroll
 
 		err := cmd.send(update)
 		if err != nil {
 			done = err
 		}
+
+		// Directories on other hosts (e.g. within ssh) can't become the cwd
+		// for local commands.
+		if term.Cwd != cwd && isLocalHost(term.CwdHost) {
+			cwd = term.Cwd
+			cmd.dir = cwd
+			if err := cmd.send(&proto.Chdir{cwd}); err != nil {
+				done = err
+			}
+		}
 	}
 
 	tr = vt100.NewTermReader(func(f func(t *vt100.Terminal)) {
@@ -265,6 +294,15 @@ func (cmd *command) run() (int, error) {
 	if err := cmd.cmd.Wait(); err != nil {
 		if eerr, ok := err.(*exec.ExitError); ok {
 			serr := eerr.Sys().(syscall.WaitStatus)
+			if serr.Signaled() && atomic.LoadInt32(&cmd.timedOut) != 0 {
+				// Report timeouts like timeout(1) does.
+				return 124, nil
+			}
+			if serr.Signaled() {
+				// Report signal deaths like sh does, as 128+signal.
+				cmd.sendError(fmt.Sprintf("terminated by signal: %s", serr.Signal()))
+				return 128 + int(serr.Signal()), nil
+			}
 			return serr.ExitStatus(), nil
 		} else {
 			return 0, err
@@ -273,6 +311,40 @@ func (cmd *command) run() (int, error) {
 	return 0, nil
 }
 
+// isLocalHost reports whether an OSC 7 host refers to this machine.
+func isLocalHost(host string) bool {
+	if host == "" || host == "localhost" {
+		return true
+	}
+	hostname, err := os.Hostname()
+	return err == nil && host == hostname
+}
+
+// chdir implements the client's cd builtin.  It checks each candidate
+// directory in turn (there are several when searching $CDPATH), and reports
+// the first one that exists back to the client.
+func (cmd *command) chdir(dirs []string) (int, error) {
+	if len(dirs) == 0 {
+		return 0, fmt.Errorf("bad arguments to cd")
+	}
+	var firstErr error
+	for _, dir := range dirs {
+		st, err := os.Stat(dir)
+		if err == nil && !st.IsDir() {
+			err = fmt.Errorf("%s: not a directory", dir)
+		}
+		if err != nil {
+			if firstErr == nil {
+				firstErr = err
+			}
+			continue
+		}
+		cmd.dir = dir
+		return 0, cmd.send(&proto.Chdir{dir})
+	}
+	return 0, firstErr
+}
+
 // runHandlingErrors calls run() and forwards any subprocess errors
 // on to the client.
 func (cmd *command) runHandlingErrors() {
@@ -281,10 +353,23 @@ func (cmd *command) runHandlingErrors() {
 		cmd.sendError(err.Error())
 		exitCode = 1
 	}
-	if exitCode < 0 {
-		exitCode = 1 // TODO: negative exit codes from signals
+	exit := &proto.Exit{
+		ExitCode: exitCode,
+		TimedOut: atomic.LoadInt32(&cmd.timedOut) != 0,
+		Git:      gitBranch(cmd.dir),
+	}
+	if state := cmd.cmd.ProcessState; state != nil {
+		if ru, ok := state.SysUsage().(*syscall.Rusage); ok {
+			cpu := time.Duration(ru.Utime.Nano() + ru.Stime.Nano())
+			exit.CpuMs = int(cpu / time.Millisecond)
+			exit.MaxRss = int(ru.Maxrss)
+			// Linux reports ru_maxrss in kilobytes, but Darwin in bytes.
+			if runtime.GOOS == "darwin" {
+				exit.MaxRss /= 1024
+			}
+		}
 	}
-	cmd.send(&proto.Exit{exitCode})
+	cmd.send(exit)
 }
 
 var localCommands = map[string]func(w io.Writer) error{
@@ -317,6 +402,81 @@ func mapPairs(m map[string]string) []proto.Pair {
 	return pairs
 }
 
+// configPath returns the path of a file in ~/.config/smash.
+func configPath(name string) (string, error) {
+	home, err := os.UserHomeDir()
+	if err != nil {
+		return "", err
+	}
+	return filepath.Join(home, ".config", "smash", name), nil
+}
+
+// readRc returns the contents of the user's rc file, ~/.config/smash/rc,
+// or "" if there is none.
+func readRc() (string, error) {
+	path, err := configPath("rc")
+	if err != nil {
+		return "", err
+	}
+	buf, err := ioutil.ReadFile(path)
+	if os.IsNotExist(err) {
+		return "", nil
+	}
+	return string(buf), err
+}
+
+// xresourcesColor matches X resources that set terminal colors.
+var xresourcesColor = regexp.MustCompile(
+	`(?m)^\s*(?:[\w-]*[.*])*(color\d+|foreground|background)\s*:`)
+
+// readThemes returns the contents of the files in ~/.config/smash/themes,
+// keyed by path.  ~/.Xresources is included if it sets any terminal colors,
+// unless the themes directory has its own theme named Xresources.  Files that
+// can't be read are skipped and reported in the returned errors.
+func readThemes() (map[string]string, []string) {
+	themes := map[string]string{}
+	var errors []string
+	haveXresources := false
+	dir, err := configPath("themes")
+	if err == nil {
+		var infos []os.FileInfo
+		infos, err = ioutil.ReadDir(dir)
+		for _, info := range infos {
+			// Skip dotfiles and editor backups, e.g. .gitkeep, .foo.swp, foo~.
+			if strings.HasPrefix(info.Name(), ".") || strings.HasSuffix(info.Name(), "~") {
+				continue
+			}
+			path := filepath.Join(dir, info.Name())
+			// Stat, rather than using info, to follow symlinks.
+			if st, err := os.Stat(path); err != nil || !st.Mode().IsRegular() {
+				continue
+			}
+			buf, err := ioutil.ReadFile(path)
+			if err != nil {
+				errors = append(errors, err.Error())
+				continue
+			}
+			themes[path] = string(buf)
+			name := strings.TrimSuffix(info.Name(), filepath.Ext(info.Name()))
+			if name == "Xresources" {
+				haveXresources = true
+			}
+		}
+	}
+	if err != nil && !os.IsNotExist(err) {
+		errors = append(errors, err.Error())
+	}
+
+	if home, err := os.UserHomeDir(); err == nil && !haveXresources {
+		path := filepath.Join(home, ".Xresources")
+		buf, err := ioutil.ReadFile(path)
+		if err == nil && xresourcesColor.Match(buf) {
+			themes[path] = string(buf)
+		}
+	}
+	return themes, errors
+}
+
 func serveWS(w http.ResponseWriter, r *http.Request) error {
 	wsConn, err := upgrader.Upgrade(w, r, nil)
 	if err != nil {
@@ -338,9 +498,21 @@ func serveWS(w http.ResponseWriter, r *http.Request) error {
 	env := getEnv()
 	env["SMASH"] = smashPath
 	env["SMASH_SOCK"] = globalSockPathForEnv
+	// Config file problems are shown by the client rather than failing the
+	// connection, which would leave the user with only a reconnect prompt.
+	errors := []string{}
+	rc, err := readRc()
+	if err != nil {
+		errors = append(errors, err.Error())
+	}
+	themes, themeErrors := readThemes()
+	errors = append(errors, themeErrors...)
 	hello := &proto.Hello{
-		Alias: mapPairs(aliases),
-		Env:   mapPairs(env),
+		Alias:  mapPairs(aliases),
+		Env:    mapPairs(env),
+		Rc:     rc,
+		Themes: mapPairs(themes),
+		Errors: errors,
 	}
 	if err = conn.writeMsg(hello); err != nil {
 		return err
@@ -460,6 +632,8 @@ func main() {
 		switch cmd {
 		case "serve":
 			err = serve()
+		case "print-integration":
+			err = printIntegration(os.Args[2:])
 		case "help":
 		default:
 			fmt.Println("TODO: usage")
@@ -108,9 +108,11 @@ type CompleteResponse struct {
 	Completions []string
 }
 type RunRequest struct {
-	Cell int
-	Cwd  string
-	Argv []string
+	Cell      int
+	Cwd       string
+	Argv      []string
+	Env       []Pair
+	TimeoutMs int
 }
 type KeyEvent struct {
 	Cell int
@@ -130,26 +132,37 @@ type Cursor struct {
 	Hidden bool
 }
 type TermUpdate struct {
-	Rows     []RowSpans
-	Cursor   Cursor
-	RowCount int
+	Rows      []RowSpans
+	Cursor    Cursor
+	RowCount  int
+	AltScreen bool
 }
 type Pair struct {
 	Key string
 	Val string
 }
 type Hello struct {
-	Alias []Pair
-	Env   []Pair
+	Alias  []Pair
+	Env    []Pair
+	Rc     string
+	Themes []Pair
+	Errors []string
 }
 type CmdError struct {
 	Error string
 }
 type Exit struct {
 	ExitCode int
+	CpuMs    int
+	MaxRss   int
+	TimedOut bool
+	Git      string
+}
+type Chdir struct {
+	Dir string
 }
 type Output struct {
-	// CmdError, TermUpdate, Exit
+	// CmdError, TermUpdate, Exit, Chdir
 	Alt Msg
 }
 type CellOutput struct {
@@ -231,6 +244,17 @@ func (msg *RunRequest) Write(w io.Writer) error {
 			return err
 		}
 	}
+	if err := WriteInt(w, len(msg.Env)); err != nil {
+		return err
+	}
+	for _, val := range msg.Env {
+		if err := val.Write(w); err != nil {
+			return err
+		}
+	}
+	if err := WriteInt(w, msg.TimeoutMs); err != nil {
+		return err
+	}
 	return nil
 }
 func (msg *KeyEvent) Write(w io.Writer) error {
@@ -292,6 +316,9 @@ func (msg *TermUpdate) Write(w io.Writer) error {
 	if err := WriteInt(w, msg.RowCount); err != nil {
 		return err
 	}
+	if err := WriteBoolean(w, msg.AltScreen); err != nil {
+		return err
+	}
 	return nil
 }
 func (msg *Pair) Write(w io.Writer) error {
@@ -320,6 +347,25 @@ func (msg *Hello) Write(w io.Writer) error {
 			return err
 		}
 	}
+	if err := WriteString(w, msg.Rc); err != nil {
+		return err
+	}
+	if err := WriteInt(w, len(msg.Themes)); err != nil {
+		return err
+	}
+	for _, val := range msg.Themes {
+		if err := val.Write(w); err != nil {
+			return err
+		}
+	}
+	if err := WriteInt(w, len(msg.Errors)); err != nil {
+		return err
+	}
+	for _, val := range msg.Errors {
+		if err := WriteString(w, val); err != nil {
+			return err
+		}
+	}
 	return nil
 }
 func (msg *CmdError) Write(w io.Writer) error {
@@ -332,6 +378,24 @@ func (msg *Exit) Write(w io.Writer) error {
 	if err := WriteInt(w, msg.ExitCode); err != nil {
 		return err
 	}
+	if err := WriteInt(w, msg.CpuMs); err != nil {
+		return err
+	}
+	if err := WriteInt(w, msg.MaxRss); err != nil {
+		return err
+	}
+	if err := WriteBoolean(w, msg.TimedOut); err != nil {
+		return err
+	}
+	if err := WriteString(w, msg.Git); err != nil {
+		return err
+	}
+	return nil
+}
+func (msg *Chdir) Write(w io.Writer) error {
+	if err := WriteString(w, msg.Dir); err != nil {
+		return err
+	}
 	return nil
 }
 func (msg *Output) Write(w io.Writer) error {
@@ -351,6 +415,11 @@ func (msg *Output) Write(w io.Writer) error {
 			return err
 		}
 		return alt.Write(w)
+	case *Chdir:
+		if err := WriteUint8(w, 4); err != nil {
+			return err
+		}
+		return alt.Write(w)
 	}
 	panic("notimpl")
 }
@@ -491,6 +560,23 @@ func (msg *RunRequest) Read(r *bufio.Reader) error {
 			msg.Argv = append(msg.Argv, val)
 		}
 	}
+	{
+		n, err := ReadInt(r)
+		if err != nil {
+			return err
+		}
+		var val Pair
+		for i := 0; i < n; i++ {
+			if err := val.Read(r); err != nil {
+				return err
+			}
+			msg.Env = append(msg.Env, val)
+		}
+	}
+	msg.TimeoutMs, err = ReadInt(r)
+	if err != nil {
+		return err
+	}
 	return nil
 }
 func (msg *KeyEvent) Read(r *bufio.Reader) error {
@@ -581,6 +667,10 @@ func (msg *TermUpdate) Read(r *bufio.Reader) error {
 	if err != nil {
 		return err
 	}
+	msg.AltScreen, err = ReadBoolean(r)
+	if err != nil {
+		return err
+	}
 	return nil
 }
 func (msg *Pair) Read(r *bufio.Reader) error {
@@ -625,6 +715,37 @@ func (msg *Hello) Read(r *bufio.Reader) error {
 			msg.Env = append(msg.Env, val)
 		}
 	}
+	msg.Rc, err = ReadString(r)
+	if err != nil {
+		return err
+	}
+	{
+		n, err := ReadInt(r)
+		if err != nil {
+			return err
+		}
+		var val Pair
+		for i := 0; i < n; i++ {
+			if err := val.Read(r); err != nil {
+				return err
+			}
+			msg.Themes = append(msg.Themes, val)
+		}
+	}
+	{
+		n, err := ReadInt(r)
+		if err != nil {
+			return err
+		}
+		var val string
+		for i := 0; i < n; i++ {
+			val, err = ReadString(r)
+			if err != nil {
+				return err
+			}
+			msg.Errors = append(msg.Errors, val)
+		}
+	}
 	return nil
 }
 func (msg *CmdError) Read(r *bufio.Reader) error {
@@ -643,6 +764,31 @@ func (msg *Exit) Read(r *bufio.Reader) error {
 	if err != nil {
 		return err
 	}
+	msg.CpuMs, err = ReadInt(r)
+	if err != nil {
+		return err
+	}
+	msg.MaxRss, err = ReadInt(r)
+	if err != nil {
+		return err
+	}
+	msg.TimedOut, err = ReadBoolean(r)
+	if err != nil {
+		return err
+	}
+	msg.Git, err = ReadString(r)
+	if err != nil {
+		return err
+	}
+	return nil
+}
+func (msg *Chdir) Read(r *bufio.Reader) error {
+	var err error
+	err = err
+	msg.Dir, err = ReadString(r)
+	if err != nil {
+		return err
+	}
 	return nil
 }
 func (msg *Output) Read(r *bufio.Reader) error {
@@ -672,6 +818,13 @@ func (msg *Output) Read(r *bufio.Reader) error {
 		}
 		msg.Alt = &val
 		return nil
+	case 4:
+		var val Chdir
+		if err := val.Read(r); err != nil {
+			return err
+		}
+		msg.Alt = &val
+		return nil
 	default:
 		return fmt.Errorf("bad tag %d when reading Output", alt)
 	}
@@ -7,6 +7,7 @@ import (
 	"io"
 	"io/ioutil"
 	"log"
+	"net/url"
 	"strings"
 	"unicode/utf8"
 )
@@ -147,6 +148,10 @@ type Terminal struct {
 
 	// Saved versions of Row/Col for the control sequence that saves/restores position.
 	SaveRow, SaveCol int
+
+	// The working directory reported by the program via OSC 7, and the host
+	// it is on.  Empty if never reported.
+	Cwd, CwdHost string
 }
 
 func NewTerminal() *Terminal {
@@ -387,6 +392,16 @@ func (tr *TermReader) readEscape(r io.ByteScanner) error {
 				t.Title = string(text)
 				// TODO: tr.Dirty
 			})
+		case 7: // current directory, as a file:// URL
+			u, err := url.Parse(string(text))
+			if err != nil || u.Scheme != "file" {
+				log.Printf("term: bad OSC 7 %q", text)
+				break
+			}
+			tr.WithTerm(func(t *Terminal) {
+				t.Cwd = u.Path
+				t.CwdHost = u.Host
+			})
 		case 10, 11, 12, 13, 14, 15, 16, 17, 18, 19: // dymamic colors
 			if string(text) == "?" {
 				tr.TODOs.Add("vt100 dynamic color query %d", n)
@@ -688,9 +703,11 @@ L:
 				// Rather than implementing an alternate screen, instead just clobber the content.
 				// This is because we don't anticipate running a series of programs within a given term,
 				// but rather just one.
-				t.Lines = make([][]Cell, t.Height)
-				t.Top = 0
-				t.CanScroll = false
+				if set {
+					t.Lines = make([][]Cell, t.Height)
+					t.Top = 0
+				}
+				t.CanScroll = !set
 				tr.Dirty.Lines[-1] = true
 			case 2004: // bracketed paste
 				tr.TODOs.Add("bracketed paste")
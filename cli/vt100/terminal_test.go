@@ -59,6 +59,14 @@ func TestTitle(t *testing.T) {
 	assert.Equal(t, "text", term.ToString())
 }
 
+func TestCwd(t *testing.T) {
+	term, tr := newTestTerminal()
+	mustRun(t, tr, "\x1b]7;file://host/home/a%20b\x07text")
+	assert.Equal(t, "/home/a b", term.Cwd)
+	assert.Equal(t, "host", term.CwdHost)
+	assert.Equal(t, "text", term.ToString())
+}
+
 func TestReset(t *testing.T) {
 	term, tr := newTestTerminal()
 	tr.Attr = 43
@@ -153,6 +161,14 @@ func TestPrivateModes(t *testing.T) {
 	assert.Equal(t, "", term.ToString())
 }
 
+func TestAltScreen(t *testing.T) {
+	term, tr := newTestTerminal()
+	mustRun(t, tr, "\x1b[?1049h")
+	assert.Equal(t, false, term.CanScroll)
+	mustRun(t, tr, "\x1b[?1049l")
+	assert.Equal(t, true, term.CanScroll)
+}
+
 func TestScrollingRegion(t *testing.T) {
 	term, tr := newTestTerminal()
 	mustRun(t, tr, "\x1b[1;24r")
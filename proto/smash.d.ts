@@ -32,6 +32,10 @@ interface RunRequest {
   cell: int;
   cwd: string;
   argv: string[];
+  /** Environment variables for the command. */
+  env: Pair[];
+  /** Kill the command after this many milliseconds, if nonzero. */
+  timeoutMs: int;
 }
 
 /** Keystroke sent to running command. */
@@ -62,6 +66,8 @@ interface TermUpdate {
   cursor: Cursor;
   /** Total count of lines in the terminal, may go down on scrolling up. */
   rowCount: int;
+  /** Whether the program is using the alternate screen, i.e. full-screen. */
+  altScreen: boolean;
 }
 
 interface Pair {
@@ -77,6 +83,15 @@ interface Hello {
   /** Environment variables. */
   env: Pair[];
 
+  /** Contents of the user's rc file, ~/.config/smash/rc, if any. */
+  rc: string;
+
+  /** Theme files, e.g. in ~/.config/smash/themes, from path to contents. */
+  themes: Pair[];
+
+  /** Problems reading the rc or theme files, shown at startup. */
+  errors: string[];
+
   // TODO: running cells and their state.
 }
 
@@ -85,8 +100,23 @@ interface CmdError {
 }
 interface Exit {
   exitCode: int;
+  /** User plus system CPU time used, in milliseconds. */
+  cpuMs: int;
+  /** Peak resident set size, in kilobytes, or 0 if not measured. */
+  maxRss: int;
+  /** Whether the command was killed for running past its timeout. */
+  timedOut: boolean;
+  /**
+   * The git branch (or abbreviated commit, if detached) of the directory the
+   * command finished in, or '' if it isn't in a git repository.
+   */
+  git: string;
+}
+/** The command changed the working directory. */
+interface Chdir {
+  dir: string;
 }
-type Output = CmdError | TermUpdate | Exit;
+type Output = CmdError | TermUpdate | Exit | Chdir;
 
 /** Message from server to client about a running subprocess. */
 interface CellOutput {
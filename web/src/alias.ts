@@ -11,11 +11,26 @@ export class AliasMap {
     this.aliases.set(alias, expansion);
   }
 
+  remove(alias: string): boolean {
+    return this.aliases.delete(alias);
+  }
+
+  /**
+   * Expands an alias in the command position.  The result is expanded again,
+   * skipping aliases already expanded, so e.g. alias ls='ls -F' terminates.
+   * A leading backslash, as in \ls, suppresses expansion.
+   */
   expand(cmd: string): string {
-    const first = cmd.split(' ')[0];
-    let exp = this.aliases.get(first);
-    if (!exp) return cmd;
-    return exp + cmd.substring(first.length);
+    if (cmd.startsWith('\\')) return cmd.substring(1);
+    const seen = new Set<string>();
+    for (;;) {
+      const first = cmd.split(' ')[0];
+      if (seen.has(first)) return cmd;
+      const exp = this.aliases.get(first);
+      if (!exp) return cmd;
+      seen.add(first);
+      cmd = exp + cmd.substring(first.length);
+    }
   }
 
   dump(): string {
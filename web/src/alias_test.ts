@@ -0,0 +1,24 @@
+import { AliasMap } from './alias';
+import { expect } from 'chai';
+
+describe('alias', () => {
+  it('expands the command word', () => {
+    const aliases = new AliasMap();
+    aliases.set('ll', 'ls -l');
+    expect(aliases.expand('ll foo')).equal('ls -l foo');
+    expect(aliases.expand('echo ll')).equal('echo ll');
+  });
+
+  it('expands recursively without looping', () => {
+    const aliases = new AliasMap();
+    aliases.set('ll', 'ls -l');
+    aliases.set('ls', 'ls -F');
+    expect(aliases.expand('ll')).equal('ls -F -l');
+  });
+
+  it('is bypassed with a backslash', () => {
+    const aliases = new AliasMap();
+    aliases.set('ls', 'ls -F');
+    expect(aliases.expand('\\ls foo')).equal('ls foo');
+  });
+});
@@ -1,13 +1,41 @@
-import { History } from './history';
 import { htext, html } from './html';
+import * as path from './path';
 import * as proto from './proto';
 import * as readline from './readline';
 import { ReadLine } from './readline';
 import * as sh from './shell';
 import { Shell } from './shell';
+import { announce, statusBar } from './statusbar';
 import { Term } from './term';
 
-const history = new History();
+/**
+ * Id for the next cell.  Ids are unique across all cell stacks, as the server
+ * identifies running commands by cell id alone.
+ */
+let nextCellId = 0;
+
+/**
+ * Cells with running commands, whose liveness indicators are refreshed on a
+ * shared timer while any are running.
+ */
+const liveCells = new Set<Cell>();
+let livenessTimer: number | undefined;
+
+function trackLiveness(cell: Cell, live: boolean) {
+  if (live) {
+    liveCells.add(cell);
+  } else {
+    liveCells.delete(cell);
+  }
+  if (liveCells.size > 0 && livenessTimer === undefined) {
+    livenessTimer = window.setInterval(() => {
+      for (const cell of liveCells) cell.updateLiveness();
+    }, 1000);
+  } else if (liveCells.size === 0 && livenessTimer !== undefined) {
+    window.clearInterval(livenessTimer);
+    livenessTimer = undefined;
+  }
+}
 
 interface PendingComplete {
   id: number;
@@ -16,17 +44,44 @@ interface PendingComplete {
 }
 
 class Cell {
-  dom = html('div', { className: 'cell' });
-  readline = new ReadLine(history);
+  dom = html('div', { className: 'cell', role: 'article' });
+  readline = new ReadLine(this.shell.history);
   term = new Term();
   /** Did the subprocess produce any output? */
   didOutput = false;
   running: sh.ExecRemote | null = null;
+  /** Working directory the command was run in. */
+  cwd = '';
+  /** The session env the command was run with. */
+  env = new Map<string, string>();
+  /** The command as spawned on the server, if it wasn't a builtin. */
+  spawned: sh.ExecRemote | undefined;
+  /** The command line, and when it was run. */
+  cmd = '';
+  startTime = 0;
+  /** When the running command last produced output. */
+  lastOutputTime = 0;
+  /** Whether the running program is full-screen, on the alternate screen. */
+  altScreen = false;
+  /** Spinner and time since last output, shown while running. */
+  livenessText = html('span');
+  liveness = html(
+    'div',
+    { className: 'liveness', 'aria-hidden': 'true' },
+    html('span', { className: 'spinner' }),
+    this.livenessText
+  );
 
   delegates = {
     /** Called when the subprocess exits. */
     exit: (id: number, exitCode: number) => {},
 
+    /** Called when the program enters or leaves the alternate screen. */
+    altScreen: (on: boolean) => {},
+
+    /** Called when the user asks to open a file referenced in the output. */
+    openFile: (path: string, line: number, col?: number) => {},
+
     /** Sends a server message. */
     send: (msg: proto.ClientMessage) => {},
   };
@@ -40,13 +95,19 @@ class Cell {
         key.cell = this.id;
         this.delegates.send({ tag: 'KeyEvent', val: key });
       },
+      openFile: (file, line, col) => {
+        this.delegates.openFile(path.join(this.cwd, file), line, col);
+      },
     };
 
     this.readline.delegates = {
       oncomplete: async (req) => {
+        // Variables come from the session env, which the server doesn't know.
+        const vars = shell.completeVar(req.input, req.pos);
+        if (vars) return vars;
         return new Promise((resolve, reject) => {
           const reqProto: proto.CompleteRequest = {
-            id: 0,
+            id: this.id,
             cwd: shell.cwd,
             input: req.input,
             pos: req.pos,
@@ -57,7 +118,7 @@ class Cell {
           };
           this.delegates.send(msg);
           this.pendingComplete = {
-            id: 0,
+            id: this.id,
             resolve,
             reject,
           };
@@ -65,29 +126,57 @@ class Cell {
       },
 
       oncommit: (cmd) => {
-        const exec = shell.exec(cmd);
-        switch (exec.kind) {
-          case 'string':
-            this.term.dom.innerText = exec.output;
-            break;
-          case 'table':
-            this.term.dom = this.renderTable(exec);
-            break;
-          case 'remote':
-            this.running = exec;
-            this.spawn(this.id, exec);
-            // The result of spawning will come back in via a message in onOutput().
-            break;
-        }
-        this.dom.appendChild(this.term.dom);
-        this.term.dom.focus();
-        if (!this.running) {
-          this.delegates.exit(this.id, 0);
-        }
+        this.cwd = shell.cwd;
+        this.env = new Map(shell.env);
+        this.run(cmd, shell.exec(cmd));
       },
     };
   }
 
+  /**
+   * Runs a past cell's command again in this prompt cell, with the cwd and
+   * env it originally ran with rather than the session's current ones.
+   */
+  rerun(from: Cell) {
+    if (!from.spawned) return;
+    this.readline.setText(from.cmd);
+    this.cwd = from.cwd;
+    this.env = new Map(from.env);
+    const { kind, cwd, cmd, timeout, timeoutMs } = from.spawned;
+    this.run(from.cmd, { kind, cwd, cmd, timeout, timeoutMs });
+  }
+
+  private run(cmd: string, exec: sh.ExecOutput) {
+    this.dom.setAttribute('aria-label', cmd);
+    this.cmd = cmd;
+    this.startTime = Date.now();
+    let exitCode = 0;
+    switch (exec.kind) {
+      case 'string':
+        this.term.dom.innerText = exec.output;
+        exitCode = exec.status ?? 0;
+        break;
+      case 'table':
+        this.term.dom = this.renderTable(exec);
+        break;
+      case 'remote':
+        this.running = exec;
+        this.spawned = exec;
+        this.spawn(this.id, exec);
+        // The result of spawning will come back in via a message in onOutput().
+        break;
+    }
+    this.dom.appendChild(this.term.dom);
+    this.term.dom.focus();
+    if (!this.running) {
+      this.delegates.exit(this.id, exitCode);
+    } else {
+      this.lastOutputTime = this.startTime;
+      this.dom.appendChild(this.liveness);
+      trackLiveness(this, true);
+    }
+  }
+
   private renderTable(exec: sh.TableOutput) {
     return html(
       'table',
@@ -110,6 +199,8 @@ class Cell {
       cell: id,
       cwd: cmd.cwd,
       argv: cmd.cmd,
+      env: Array.from(this.env).map(([key, val]) => ({ key, val })),
+      timeoutMs: cmd.timeoutMs ?? 0,
     };
     this.delegates.send({ tag: 'RunRequest', val: run });
   }
@@ -122,30 +213,98 @@ class Cell {
         break;
       case 'TermUpdate':
         this.didOutput = true;
+        this.lastOutputTime = Date.now();
         this.term.onUpdate(msg.val);
+        if (msg.val.altScreen !== this.altScreen) {
+          this.altScreen = msg.val.altScreen;
+          this.delegates.altScreen(this.altScreen);
+        }
         break;
       case 'Exit':
         // exit code
         // Command completed.
         const exitCode = msg.val.exitCode;
+        this.shell.gitBranch = msg.val.git;
+        if (msg.val.timedOut) {
+          const text = `timed out after ${this.running?.timeout}`;
+          this.dom.appendChild(html('div', {}, htext(text)));
+        }
         if (this.running && this.running.onComplete) {
           this.running.onComplete(exitCode);
         }
+        // cd is run by the server, but is over as soon as it's typed.
+        const instant = this.running?.cmd[0] === 'cd' && exitCode === 0;
         this.running = null;
+        if (this.altScreen) {
+          this.altScreen = false;
+          this.delegates.altScreen(false);
+        }
+        trackLiveness(this, false);
+        this.liveness.remove();
         this.term.showCursor(false);
         this.term.preventFocus();
         if (!this.didOutput) {
           // Remove the vertical space of the terminal.
           this.term.dom.innerText = '';
         }
+        this.showUsage(msg.val);
+        if (!instant) this.announceExit(exitCode);
         this.delegates.exit(this.id, exitCode);
+        break;
+      case 'Chdir':
+        this.running?.onChdir?.(msg.val.dir);
+        break;
+    }
+  }
+
+  /** Refreshes the time since the running command last produced output. */
+  updateLiveness() {
+    const secs = Math.floor((Date.now() - this.lastOutputTime) / 1000);
+    // Don't distract while output is flowing.
+    if (secs < 2) {
+      this.livenessText.innerText = '';
+    } else if (this.didOutput) {
+      this.livenessText.innerText = `last output ${secs} s ago`;
+    } else {
+      this.livenessText.innerText = `no output for ${secs} s`;
+    }
+  }
+
+  /**
+   * Shows how long the command took and what it used, below the output of
+   * commands that ran a while and on hover for all.  There's no usage for
+   * commands like cd that the server runs itself, or that failed to start.
+   */
+  private showUsage(exit: proto.Exit) {
+    if (exit.maxRss === 0) return;
+    const ms = Date.now() - this.startTime;
+    const secs = (ms: number) => `${(ms / 1000).toFixed(1)} s`;
+    const mb = (exit.maxRss / 1024).toFixed(1);
+    const text = `ran ${secs(ms)}, cpu ${secs(exit.cpuMs)}, max rss ${mb} MB`;
+    this.dom.title = text;
+    if (ms >= 1000) {
+      this.dom.appendChild(html('div', { className: 'usage' }, htext(text)));
     }
   }
 
+  /**
+   * Tells screen readers that the command finished, depending on the
+   * $SMASH_ANNOUNCE verbosity: 'all' (the default), 'errors', or 'off'.
+   */
+  private announceExit(exitCode: number) {
+    const verbosity = this.shell.env.get('SMASH_ANNOUNCE') || 'all';
+    if (verbosity === 'off') return;
+    if (verbosity === 'errors' && exitCode === 0) return;
+    const secs = Math.round((Date.now() - this.startTime) / 1000);
+    const status =
+      exitCode === 0 ? 'finished' : `exited with status ${exitCode}`;
+    announce(`${this.cmd} ${status} after ${secs} s`);
+  }
+
   onCompleteResponse(msg: proto.CompleteResponse) {
     if (!this.pendingComplete) return;
     this.pendingComplete.resolve({
-      completions: msg.completions,
+      completions: msg.completions.map(sh.escape),
       pos: msg.pos,
     });
     this.pendingComplete = undefined;
@@ -160,6 +319,21 @@ class Cell {
   }
 }
 
+/**
+ * Expands an editor URL template like "vscode://file/{file}:{line}:{col}".
+ */
+export function expandEditorURL(
+  template: string,
+  file: string,
+  line: number,
+  col = 1
+): string {
+  return template
+    .replace('{file}', encodeURI(file))
+    .replace('{line}', String(line))
+    .replace('{col}', String(col));
+}
+
 function scrollToBottom(el: HTMLElement) {
   el.scrollIntoView({
     block: 'end',
@@ -167,42 +341,225 @@ function scrollToBottom(el: HTMLElement) {
 }
 
 export class CellStack {
-  dom = html('div', { className: 'cellstack' });
+  dom = html('div', { className: 'cellstack', role: 'feed' });
   cells: Cell[] = [];
   delegates = {
     send: (msg: proto.ClientMessage) => {},
   };
 
+  /** The cell currently expanded to fill the stack, if any. */
+  zoomed: Cell | undefined;
+
+  /** Whether the stack had focus before the last output was handled. */
+  private hadFocus = false;
+
+  /** The cell the user last clicked in, as finished cells can't take focus. */
+  clicked: Cell | undefined;
+
+  /**
+   * Whether to keep the bottom of the stack in view as output arrives.
+   * Disengaged when the user scrolls up, reengaged on scrolling to the bottom.
+   */
+  follow = true;
+
   constructor(readonly shell: Shell) {
+    this.dom.addEventListener('keydown', (ev) => this.onKeyDown(ev));
+    this.dom.addEventListener('scroll', () => {
+      const { scrollTop, clientHeight, scrollHeight } = this.dom;
+      this.follow = scrollTop + clientHeight >= scrollHeight - 2;
+    });
+    this.dom.addEventListener('mousedown', (ev) => {
+      const cell = this.cells.find((c) => c.dom.contains(ev.target as Node));
+      if (cell) this.clicked = cell;
+    });
     this.addNew();
   }
 
-  addNew() {
-    const id = this.cells.length;
+  private onKeyDown(ev: KeyboardEvent) {
+    if (ev.defaultPrevented) return;
+    const page = this.dom.clientHeight * 0.9;
+    switch (readline.translateKey(ev)) {
+      case 'C-S-Enter':
+        this.toggleZoom();
+        break;
+      case 'M-S-Enter':
+        this.rerun();
+        break;
+      case 'PageUp':
+        this.dom.scrollBy(0, -page);
+        break;
+      case 'PageDown':
+        this.dom.scrollBy(0, page);
+        break;
+      case 'C-Home':
+        this.dom.scrollTop = 0;
+        break;
+      case 'C-End':
+        this.dom.scrollTop = this.dom.scrollHeight;
+        break;
+      default:
+        return;
+    }
+    ev.preventDefault();
+  }
+
+  /**
+   * Returns the cell to zoom: the focused cell, unless that is the prompt, then
+   * the last clicked cell, then the last cell that ran a command.
+   */
+  private getZoomTarget(): Cell | undefined {
+    const ran = (cell?: Cell) => !!cell && cell.cmd !== '';
+    const focused = this.cells.find((cell) =>
+      cell.dom.contains(document.activeElement)
+    );
+    if (ran(focused)) return focused;
+    if (ran(this.clicked)) return this.clicked;
+    return this.cells
+      .slice()
+      .reverse()
+      .find((cell) => ran(cell));
+  }
+
+  /**
+   * Toggles expanding the focused or last clicked cell to fill the stack,
+   * hiding all other cells except the current prompt.
+   */
+  toggleZoom() {
+    if (this.zoomed) {
+      this.unzoom();
+      return;
+    }
+    const cell = this.getZoomTarget();
+    if (cell) this.zoom(cell);
+  }
+
+  private zoom(cell: Cell) {
+    this.zoomed?.dom.classList.remove('zoom');
+    this.zoomed = cell;
+    cell.dom.classList.add('zoom');
+    this.dom.classList.add('zoomed');
+    cell.dom.scrollIntoView();
+  }
+
+  private unzoom() {
+    if (!this.zoomed) return;
+    this.zoomed.dom.classList.remove('zoom');
+    this.dom.classList.remove('zoomed');
+    this.zoomed = undefined;
+    this.follow = true;
+    scrollToBottom(this.getLastCell().dom);
+  }
+
+  /**
+   * Gives full-screen programs like vim the whole stack while they are on the
+   * alternate screen, returning to the stacked view when they leave it.
+   */
+  private onAltScreen(cell: Cell, on: boolean) {
+    if (on) {
+      this.zoom(cell);
+    } else if (this.zoomed === cell) {
+      this.unzoom();
+    }
+  }
+
+  /**
+   * Re-runs the command of the focused or last clicked cell (as for zoom) at
+   * the prompt, with the cwd and env it originally ran with.
+   */
+  rerun() {
+    const from = this.getZoomTarget();
+    const prompt = this.getLastCell();
+    if (!from || prompt.running || prompt.cmd) return;
+    if (!from.spawned) {
+      statusBar.showMessage("can't re-run builtins");
+      return;
+    }
+    if (prompt.readline.input.value) {
+      statusBar.showMessage("can't re-run: the prompt isn't empty");
+      return;
+    }
+    prompt.rerun(from);
+  }
+
+  /** Adds a new prompt cell, focusing it unless focus is false. */
+  addNew(focus = true) {
+    const id = nextCellId++;
     const cell = new Cell(id, this.shell);
-    cell.readline.setPrompt(this.shell.cwdForPrompt());
+    cell.readline.setPrompt(this.shell.prompt());
     cell.delegates = {
       send: (msg) => this.delegates.send(msg),
       exit: (id: number, exitCode: number) => {
         this.onExit(id, exitCode);
       },
+      altScreen: (on) => this.onAltScreen(cell, on),
+      openFile: (file, line, col) => {
+        this.openFile(file, line, col);
+      },
     };
     this.cells.push(cell);
     this.dom.appendChild(cell.dom);
-    cell.readline.input.focus();
-    scrollToBottom(cell.dom);
+    if (focus) cell.readline.input.focus({ preventScroll: !this.follow });
+    if (this.follow) scrollToBottom(cell.dom);
+  }
+
+  /** Shows an error as an entry of its own, above the prompt. */
+  addError(text: string) {
+    const dom = html(
+      'div',
+      { className: 'cell error', role: 'article' },
+      html('pre', {}, htext(text))
+    );
+    this.dom.insertBefore(dom, this.getLastCell().dom);
+  }
+
+  /** Returns whether any cell has a command still running. */
+  isRunning(): boolean {
+    return this.cells.some((cell) => cell.running);
+  }
+
+  findCell(id: number): Cell | undefined {
+    return this.cells.find((cell) => cell.id === id);
   }
 
   onOutput(msg: proto.CellOutput) {
-    const cell = this.cells[msg.cell];
+    const cell = this.findCell(msg.cell);
+    if (!cell) return;
+    // An exiting cell gives up focus, so note beforehand whether we had it.
+    this.hadFocus = this.dom.contains(document.activeElement);
     cell.onOutput(msg.output);
-    if (msg.cell === this.cells.length - 1) {
+    if (this.follow && cell === this.getLastCell()) {
       scrollToBottom(cell.dom);
     }
   }
 
   onExit(id: number, exitCode: number) {
-    this.addNew();
+    this.shell.exitCode = exitCode;
+    // Don't steal focus from another pane the user is typing in.
+    const focus = this.hadFocus || this.dom.contains(document.activeElement);
+    this.hadFocus = false;
+    this.addNew(focus);
+  }
+
+  /**
+   * Opens a file in the user's editor, either via an editor URL like
+   * vscode:// (from $SMASH_EDITOR_URL) or by running $EDITOR as a command.
+   */
+  openFile(file: string, line: number, col?: number) {
+    const url = this.shell.env.get('SMASH_EDITOR_URL');
+    if (url) {
+      window.open(expandEditorURL(url, file, line, col));
+      return;
+    }
+    const cell = this.getLastCell();
+    if (cell.running) return;
+    if (cell.readline.input.value) {
+      statusBar.showMessage("can't open file: the prompt isn't empty");
+      return;
+    }
+    // $EDITOR is left unquoted, as it may carry arguments like "code -w".
+    const editor = this.shell.env.get('EDITOR') || 'vi';
+    cell.readline.setText(`${editor} +${line} ${sh.quote(file)}`);
+    cell.readline.onEnter();
   }
 
   getLastCell(): Cell {
@@ -0,0 +1,49 @@
+interface Visits {
+  count: number;
+  /** Time of the last visit, in ms since the epoch. */
+  last: number;
+}
+
+/**
+ * Tracks visited directories, ranking them by "frecency" as z does: the
+ * number of visits, weighted by how recent the last visit was.
+ */
+export class Frecency {
+  private dirs = new Map<string, Visits>();
+
+  visit(dir: string, now = Date.now()) {
+    const visits = this.dirs.get(dir) ?? { count: 0, last: now };
+    visits.count++;
+    visits.last = now;
+    this.dirs.set(dir, visits);
+  }
+
+  score(dir: string, now = Date.now()): number {
+    const visits = this.dirs.get(dir);
+    if (!visits) return 0;
+    const age = now - visits.last;
+    const hour = 60 * 60 * 1000;
+    let weight = 0.25;
+    if (age < hour) weight = 4;
+    else if (age < 24 * hour) weight = 2;
+    else if (age < 7 * 24 * hour) weight = 0.5;
+    return visits.count * weight;
+  }
+
+  /**
+   * Returns the visited directories matching all the fragments, in order, best
+   * first.  Matching ignores case.
+   */
+  match(fragments: string[], now = Date.now()): string[] {
+    const matches = Array.from(this.dirs.keys()).filter((dir) => {
+      let ofs = 0;
+      for (const frag of fragments) {
+        ofs = dir.toLowerCase().indexOf(frag.toLowerCase(), ofs);
+        if (ofs === -1) return false;
+        ofs += frag.length;
+      }
+      return true;
+    });
+    return matches.sort((a, b) => this.score(b, now) - this.score(a, now));
+  }
+}
@@ -0,0 +1,27 @@
+import { Frecency } from './frecency';
+import { expect } from 'chai';
+
+describe('frecency', () => {
+  const hour = 60 * 60 * 1000;
+
+  it('matches fragments in order', () => {
+    const dirs = new Frecency();
+    dirs.visit('/home/a/src/smash');
+    dirs.visit('/home/a/Downloads');
+    expect(dirs.match(['src', 'sm'])).deep.equal(['/home/a/src/smash']);
+    expect(dirs.match(['sm', 'src'])).deep.equal([]);
+    expect(dirs.match(['down'])).deep.equal(['/home/a/Downloads']);
+  });
+
+  it('ranks frequent and recent directories first', () => {
+    const dirs = new Frecency();
+    const now = 100 * hour;
+    dirs.visit('/old', now - 48 * hour);
+    dirs.visit('/old', now - 48 * hour);
+    dirs.visit('/old', now - 48 * hour);
+    dirs.visit('/new', now);
+    expect(dirs.match(['/'], now)).deep.equal(['/new', '/old']);
+    dirs.visit('/old', now);
+    expect(dirs.match(['/'], now)).deep.equal(['/old', '/new']);
+  });
+});
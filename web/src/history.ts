@@ -17,4 +17,16 @@ export class History {
     if (ofs > this.entries.length) return;
     return this.entries[this.entries.length - ofs];
   }
+
+  /** Returns all entries, oldest first. */
+  list(): string[] {
+    return this.entries;
+  }
+
+  /** Removes the entry at index, returning false if there is none. */
+  remove(index: number): boolean {
+    if (!(index >= 0 && index < this.entries.length)) return false;
+    this.entries.splice(index, 1);
+    return true;
+  }
 }
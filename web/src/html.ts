@@ -10,6 +10,9 @@ export function html(
       for (const key in style) {
         (tag.style as any)[key] = style[key];
       }
+    } else if (key === 'role' || key.startsWith('aria-')) {
+      // Set as attributes, as not all browsers reflect ARIA properties.
+      tag.setAttribute(key, String(attr[key]));
     } else {
       (tag as any)[key] = attr[key];
     }
@@ -0,0 +1,284 @@
+import { CellStack } from './cells';
+import { html } from './html';
+import * as proto from './proto';
+import { translateKey } from './readline';
+import { Shell } from './shell';
+import { statusBar } from './statusbar';
+
+type Direction = 'row' | 'column';
+type Pane = CellStack | Split;
+
+/** Minimum size in pixels that a pane can be resized to. */
+const minPaneSize = 40;
+
+/**
+ * A container laying out panes side by side ('row') or stacked ('column').
+ * The gaps between panes can be dragged to resize them.
+ */
+class Split {
+  dom = html('div');
+  children: Pane[] = [];
+
+  constructor(public dir: Direction) {
+    this.setDir(dir);
+    this.dom.addEventListener('mousedown', (ev) => this.onMouseDown(ev));
+  }
+
+  setDir(dir: Direction) {
+    this.dir = dir;
+    this.dom.className = `split ${dir}`;
+  }
+
+  insert(index: number, pane: Pane) {
+    this.dom.insertBefore(pane.dom, this.children[index]?.dom ?? null);
+    this.children.splice(index, 0, pane);
+    this.setSizes(this.children.map(() => 1));
+  }
+
+  replace(old: Pane, pane: Pane) {
+    const index = this.children.indexOf(old);
+    pane.dom.style.flex = old.dom.style.flex;
+    this.dom.replaceChild(pane.dom, old.dom);
+    this.children[index] = pane;
+  }
+
+  remove(pane: Pane) {
+    this.dom.removeChild(pane.dom);
+    this.children.splice(this.children.indexOf(pane), 1);
+    this.setSizes(this.children.map(() => 1));
+  }
+
+  /** Returns the current size of each child along the split direction. */
+  private getSizes(): number[] {
+    return this.children.map((c) => {
+      const rect = c.dom.getBoundingClientRect();
+      return this.dir === 'row' ? rect.width : rect.height;
+    });
+  }
+
+  /** Sets the relative sizes of the children. */
+  private setSizes(sizes: number[]) {
+    this.children.forEach((c, i) => {
+      c.dom.style.flex = `${sizes[i]} 1 0`;
+    });
+  }
+
+  /**
+   * Moves the boundary after the child at index by delta pixels, trading space
+   * between it and the following child.
+   */
+  private moveBoundary(sizes: number[], index: number, delta: number) {
+    const total = sizes[index] + sizes[index + 1];
+    const size = Math.max(
+      minPaneSize,
+      Math.min(total - minPaneSize, sizes[index] + delta)
+    );
+    sizes = sizes.slice();
+    sizes[index] = size;
+    sizes[index + 1] = total - size;
+    this.setSizes(sizes);
+  }
+
+  /** Grows (or with negative delta, shrinks) a child by delta pixels. */
+  resize(pane: Pane, delta: number) {
+    const index = this.children.indexOf(pane);
+    if (this.children.length < 2) return;
+    if (index < this.children.length - 1) {
+      this.moveBoundary(this.getSizes(), index, delta);
+    } else {
+      this.moveBoundary(this.getSizes(), index - 1, -delta);
+    }
+  }
+
+  private onMouseDown(ev: MouseEvent) {
+    // Only mouse downs on the gaps between children hit the split itself.
+    if (ev.target !== this.dom) return;
+    const pos = (e: MouseEvent) => (this.dir === 'row' ? e.clientX : e.clientY);
+    const start = pos(ev);
+    // The boundary being dragged follows the last child starting before it.
+    const index =
+      this.children.filter((c) => {
+        const rect = c.dom.getBoundingClientRect();
+        return (this.dir === 'row' ? rect.left : rect.top) < start;
+      }).length - 1;
+    if (index < 0 || index >= this.children.length - 1) return;
+
+    const sizes = this.getSizes();
+    const onMove = (e: MouseEvent) => {
+      this.moveBoundary(sizes, index, pos(e) - start);
+    };
+    const onUp = () => {
+      document.removeEventListener('mousemove', onMove);
+      document.removeEventListener('mouseup', onUp);
+    };
+    document.addEventListener('mousemove', onMove);
+    document.addEventListener('mouseup', onUp);
+    ev.preventDefault();
+  }
+}
+
+/**
+ * The contents of a tab: one or more independent cell stacks, each with its
+ * own shell state, arranged in nested splits.
+ */
+export class Panes {
+  private root = new Split('row');
+  dom = this.root.dom;
+
+  /** The most recently focused cell stack. */
+  focused: CellStack;
+
+  delegates = {
+    send: (msg: proto.ClientMessage) => {},
+  };
+
+  constructor(shell: Shell) {
+    this.focused = this.newStack(shell);
+    this.root.insert(0, this.focused);
+    this.dom.addEventListener('keydown', (ev) => this.onKeyDown(ev));
+    this.dom.addEventListener('focusin', (ev) => {
+      const stack = this.stacks().find((s) =>
+        s.dom.contains(ev.target as Node)
+      );
+      if (stack) this.focused = stack;
+    });
+  }
+
+  private newStack(shell: Shell): CellStack {
+    const stack = new CellStack(shell);
+    stack.delegates = {
+      send: (msg) => this.delegates.send(msg),
+    };
+    return stack;
+  }
+
+  private onKeyDown(ev: KeyboardEvent) {
+    if (ev.defaultPrevented) return;
+    switch (translateKey(ev)) {
+      case 'C-|':
+        this.split('row');
+        break;
+      case 'C-_':
+        this.split('column');
+        break;
+      case 'C-S-Backspace':
+        this.close();
+        break;
+      case 'M-ArrowRight':
+      case 'M-ArrowDown':
+        this.focusNext(1);
+        break;
+      case 'M-ArrowLeft':
+      case 'M-ArrowUp':
+        this.focusNext(-1);
+        break;
+      case 'M-S-ArrowRight':
+        this.resizeFocused('row', 20);
+        break;
+      case 'M-S-ArrowLeft':
+        this.resizeFocused('row', -20);
+        break;
+      case 'M-S-ArrowDown':
+        this.resizeFocused('column', 20);
+        break;
+      case 'M-S-ArrowUp':
+        this.resizeFocused('column', -20);
+        break;
+      default:
+        return;
+    }
+    ev.preventDefault();
+  }
+
+  /** Returns all cell stacks, in layout order. */
+  stacks(): CellStack[] {
+    const stacks: CellStack[] = [];
+    const visit = (pane: Pane) => {
+      if (pane instanceof Split) pane.children.forEach(visit);
+      else stacks.push(pane);
+    };
+    visit(this.root);
+    return stacks;
+  }
+
+  private parentOf(pane: Pane, split = this.root): Split | undefined {
+    for (const child of split.children) {
+      if (child === pane) return split;
+      if (child instanceof Split) {
+        const parent = this.parentOf(pane, child);
+        if (parent) return parent;
+      }
+    }
+  }
+
+  /**
+   * Resizes the focused stack along an axis, by resizing its ancestor within
+   * the nearest enclosing split laid out in that direction.
+   */
+  private resizeFocused(dir: Direction, delta: number) {
+    let pane: Pane = this.focused;
+    let parent = this.parentOf(pane);
+    while (parent && parent.dir !== dir) {
+      pane = parent;
+      parent = this.parentOf(pane);
+    }
+    parent?.resize(pane, delta);
+  }
+
+  /** Splits the focused stack, placing a new stack after it. */
+  split(dir: Direction) {
+    const stack = this.newStack(this.focused.shell.fork());
+    const parent = this.parentOf(this.focused)!;
+    if (parent.children.length === 1) parent.setDir(dir);
+    if (parent.dir === dir) {
+      parent.insert(parent.children.indexOf(this.focused) + 1, stack);
+    } else {
+      const split = new Split(dir);
+      parent.replace(this.focused, split);
+      split.insert(0, this.focused);
+      split.insert(1, stack);
+    }
+    this.focused = stack;
+    stack.focus();
+  }
+
+  /**
+   * Closes the focused stack, unless it is the only one or has a command
+   * still running, as nothing would stop the command.
+   */
+  close() {
+    const stacks = this.stacks();
+    if (stacks.length === 1) {
+      statusBar.showMessage("can't close the last pane");
+      return;
+    }
+    if (this.focused.isRunning()) {
+      statusBar.showMessage("can't close a pane with a running command");
+      return;
+    }
+    const index = stacks.indexOf(this.focused);
+    const parent = this.parentOf(this.focused)!;
+    parent.remove(this.focused);
+    if (parent.children.length === 1 && parent !== this.root) {
+      this.parentOf(parent)!.replace(parent, parent.children[0]);
+    }
+    this.focused = stacks[index > 0 ? index - 1 : 1];
+    this.focused.focus();
+  }
+
+  focusNext(delta: number) {
+    const stacks = this.stacks();
+    const index = stacks.indexOf(this.focused);
+    this.focused = stacks[(index + delta + stacks.length) % stacks.length];
+    this.focused.focus();
+  }
+
+  /** Finds the cell stack containing the given cell id. */
+  findStack(cellId: number): CellStack | undefined {
+    return this.stacks().find((stack) => stack.findCell(cellId));
+  }
+
+  focus() {
+    this.focused.focus();
+  }
+}
@@ -4,7 +4,7 @@
 
 export function join(a: string, b: string): string {
   if (b.startsWith('/')) return b;
-  if (a.endsWith('/')) return a;
+  if (a.endsWith('/')) return normalize(a + b);
   return normalize(`${a}/${b}`);
 }
 
@@ -7,6 +7,10 @@ describe('path', () => {
       expect(path.join('a', 'b')).equal('a/b');
     });
 
+    it('joins onto root', () => {
+      expect(path.join('/', 'a')).equal('/a');
+    });
+
     it('obeys parents', () => {
       expect(path.join('a/b', '../c')).equal('a/c');
     });
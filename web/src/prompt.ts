@@ -0,0 +1,88 @@
+import { ansiNames } from './theme';
+
+/**
+ * The prompt template language.  A template is text with tags in braces:
+ *
+ *   {cwd}, {status}, ...   a value, looked up by the caller; {$VAR} is an
+ *                          environment variable
+ *   {red} ... {reset}      a color change; any ANSI color name, or
+ *                          bright-NAME
+ *   {if COND} ... {end}    a conditional, where COND is NAME, true when the
+ *                          value is non-empty, or NAME==TEXT or NAME!=TEXT;
+ *                          {else} is allowed inside
+ *   {{                     a literal '{'
+ */
+
+/** A run of prompt text, optionally in a theme color (a CSS variable). */
+export interface PromptSpan {
+  text: string;
+  color?: string;
+}
+
+/** The default prompt: the cwd, with the exit status if it failed. */
+export const defaultPrompt = '{if status!=0}[{status}] {end}{cwd}$ ';
+
+/** Returns the theme's CSS variable for a color name, if it is one. */
+function colorVar(name: string): string | undefined {
+  const bright = name.startsWith('bright-');
+  const index = ansiNames.indexOf(bright ? name.substring(7) : name);
+  if (index === -1) return;
+  return `${bright ? 'bright' : 'color'}${index + 1}`;
+}
+
+function evalCond(cond: string, lookup: (name: string) => string): boolean {
+  const m = cond.match(/^(\S+?)\s*(==|!=)\s*(.*)$/);
+  if (!m) return lookup(cond) !== '';
+  const [, name, op, text] = m;
+  return (lookup(name) === text) === (op === '==');
+}
+
+/** Expands a prompt template, looking up values with lookup. */
+export function expandPrompt(
+  template: string,
+  lookup: (name: string) => string
+): PromptSpan[] {
+  const spans: PromptSpan[] = [];
+  let color: string | undefined;
+  // Whether each enclosing {if} is taking its branch.
+  const conds: boolean[] = [];
+  const active = () => conds.every((c) => c);
+
+  const emit = (text: string) => {
+    if (!text || !active()) return;
+    const last = spans[spans.length - 1];
+    if (last && last.color === color) {
+      last.text += text;
+    } else {
+      spans.push({ text, color });
+    }
+  };
+
+  const tags = /\{\{|\{([^{}]*)\}/g;
+  let ofs = 0;
+  let m: RegExpExecArray | null;
+  while ((m = tags.exec(template))) {
+    emit(template.substring(ofs, m.index));
+    ofs = tags.lastIndex;
+    if (m[0] === '{{') {
+      emit('{');
+      continue;
+    }
+    const tag = m[1].trim();
+    if (tag.startsWith('if ')) {
+      conds.push(evalCond(tag.substring(3).trim(), lookup));
+    } else if (tag === 'else') {
+      conds.push(!conds.pop());
+    } else if (tag === 'end') {
+      conds.pop();
+    } else if (tag === 'reset') {
+      if (active()) color = undefined;
+    } else if (colorVar(tag)) {
+      if (active()) color = colorVar(tag);
+    } else {
+      emit(lookup(tag));
+    }
+  }
+  emit(template.substring(ofs));
+  return spans;
+}
@@ -0,0 +1,37 @@
+import { defaultPrompt, expandPrompt } from './prompt';
+import { expect } from 'chai';
+
+describe('prompt', () => {
+  const vars = new Map([
+    ['cwd', '~/src'],
+    ['status', '0'],
+    ['user', 'evmar'],
+  ]);
+  const lookup = (name: string) => vars.get(name) ?? '';
+  const text = (template: string) =>
+    expandPrompt(template, lookup)
+      .map((span) => span.text)
+      .join('');
+
+  it('expands values', () => {
+    expect(text('{user}:{cwd}$ ')).equal('evmar:~/src$ ');
+    expect(text('{{cwd}')).equal('{cwd}');
+  });
+
+  it('evaluates conditionals', () => {
+    expect(text('{if status!=0}[{status}] {end}{cwd}')).equal('~/src');
+    expect(text('{if status==0}ok{else}fail{end}')).equal('ok');
+    expect(text('{if user}{if missing}x{else}y{end}{end}')).equal('y');
+    expect(text(defaultPrompt)).equal('~/src$ ');
+  });
+
+  it('colors spans', () => {
+    expect(expandPrompt('{blue}{cwd}{reset}$ ', lookup)).deep.equal([
+      { text: '~/src', color: 'color5' },
+      { text: '$ ', color: undefined },
+    ]);
+    expect(expandPrompt('{bright-red}!', lookup)).deep.equal([
+      { text: '!', color: 'bright2' },
+    ]);
+  });
+});
@@ -19,6 +19,8 @@ export interface RunRequest {
   cell: number;
   cwd: string;
   argv: string[];
+  env: Pair[];
+  timeoutMs: number;
 }
 export interface KeyEvent {
   cell: number;
@@ -41,6 +43,7 @@ export interface TermUpdate {
   rows: RowSpans[];
   cursor: Cursor;
   rowCount: number;
+  altScreen: boolean;
 }
 export interface Pair {
   key: string;
@@ -49,17 +52,28 @@ export interface Pair {
 export interface Hello {
   alias: Pair[];
   env: Pair[];
+  rc: string;
+  themes: Pair[];
+  errors: string[];
 }
 export interface CmdError {
   error: string;
 }
 export interface Exit {
   exitCode: number;
+  cpuMs: number;
+  maxRss: number;
+  timedOut: boolean;
+  git: string;
+}
+export interface Chdir {
+  dir: string;
 }
 export type Output =
   | { tag: 'CmdError'; val: CmdError }
   | { tag: 'TermUpdate'; val: TermUpdate }
-  | { tag: 'Exit'; val: Exit };
+  | { tag: 'Exit'; val: Exit }
+  | { tag: 'Chdir'; val: Chdir };
 export interface CellOutput {
   cell: number;
   output: Output;
@@ -145,6 +159,8 @@ export class Reader {
       cell: this.readInt(),
       cwd: this.readString(),
       argv: this.readArray(() => this.readString()),
+      env: this.readArray(() => this.readPair()),
+      timeoutMs: this.readInt(),
     };
   }
   readKeyEvent(): KeyEvent {
@@ -177,6 +193,7 @@ export class Reader {
       rows: this.readArray(() => this.readRowSpans()),
       cursor: this.readCursor(),
       rowCount: this.readInt(),
+      altScreen: this.readBoolean(),
     };
   }
   readPair(): Pair {
@@ -189,6 +206,9 @@ export class Reader {
     return {
       alias: this.readArray(() => this.readPair()),
       env: this.readArray(() => this.readPair()),
+      rc: this.readString(),
+      themes: this.readArray(() => this.readPair()),
+      errors: this.readArray(() => this.readString()),
     };
   }
   readCmdError(): CmdError {
@@ -199,6 +219,15 @@ export class Reader {
   readExit(): Exit {
     return {
       exitCode: this.readInt(),
+      cpuMs: this.readInt(),
+      maxRss: this.readInt(),
+      timedOut: this.readBoolean(),
+      git: this.readString(),
+    };
+  }
+  readChdir(): Chdir {
+    return {
+      dir: this.readString(),
     };
   }
   readOutput(): Output {
@@ -209,6 +238,8 @@ export class Reader {
         return { tag: 'TermUpdate', val: this.readTermUpdate() };
       case 3:
         return { tag: 'Exit', val: this.readExit() };
+      case 4:
+        return { tag: 'Chdir', val: this.readChdir() };
       default:
         throw new Error('parse error');
     }
@@ -302,6 +333,10 @@ export class Writer {
     this.writeArray(msg.argv, (val) => {
       this.writeString(val);
     });
+    this.writeArray(msg.env, (val) => {
+      this.writePair(val);
+    });
+    this.writeInt(msg.timeoutMs);
   }
   writeKeyEvent(msg: KeyEvent) {
     this.writeInt(msg.cell);
@@ -328,6 +363,7 @@ export class Writer {
     });
     this.writeCursor(msg.cursor);
     this.writeInt(msg.rowCount);
+    this.writeBoolean(msg.altScreen);
   }
   writePair(msg: Pair) {
     this.writeString(msg.key);
@@ -340,12 +376,26 @@ export class Writer {
     this.writeArray(msg.env, (val) => {
       this.writePair(val);
     });
+    this.writeString(msg.rc);
+    this.writeArray(msg.themes, (val) => {
+      this.writePair(val);
+    });
+    this.writeArray(msg.errors, (val) => {
+      this.writeString(val);
+    });
   }
   writeCmdError(msg: CmdError) {
     this.writeString(msg.error);
   }
   writeExit(msg: Exit) {
     this.writeInt(msg.exitCode);
+    this.writeInt(msg.cpuMs);
+    this.writeInt(msg.maxRss);
+    this.writeBoolean(msg.timedOut);
+    this.writeString(msg.git);
+  }
+  writeChdir(msg: Chdir) {
+    this.writeString(msg.dir);
   }
   writeOutput(msg: Output) {
     switch (msg.tag) {
@@ -361,6 +411,10 @@ export class Writer {
         this.writeUint8(3);
         this.writeExit(msg.val);
         break;
+      case 'Chdir':
+        this.writeUint8(4);
+        this.writeChdir(msg.val);
+        break;
     }
   }
   writeCellOutput(msg: CellOutput) {
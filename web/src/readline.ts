@@ -1,4 +1,5 @@
 import { html, htext } from './html';
+import { PromptSpan } from './prompt';
 
 export function translateKey(ev: KeyboardEvent): string {
   switch (ev.key) {
@@ -30,7 +31,11 @@ export interface CompleteResponse {
 }
 
 class CompletePopup {
-  dom = html('div', { className: 'popup', style: { overflow: 'hidden' } });
+  dom = html('div', {
+    className: 'popup',
+    role: 'listbox',
+    style: { overflow: 'hidden' },
+  });
   textSize!: { width: number; height: number };
   selection = -1;
 
@@ -47,7 +52,11 @@ class CompletePopup {
     );
 
     for (const comp of this.resp.completions) {
-      const dom = html('div', { className: 'completion' }, htext(comp));
+      const dom = html(
+        'div',
+        { className: 'completion', role: 'option' },
+        htext(comp)
+      );
       // Listen to mousedown because if we listen to click, the click causes
       // the input field to lose focus.
       dom.addEventListener('mousedown', (event) => {
@@ -138,10 +147,12 @@ class CompletePopup {
   private selectCompletion(index: number) {
     if (this.selection !== -1) {
       this.dom.children[this.selection].classList.remove('selected');
+      this.dom.children[this.selection].setAttribute('aria-selected', 'false');
     }
     this.selection =
       (index + this.resp.completions.length) % this.resp.completions.length;
     this.dom.children[this.selection].classList.add('selected');
+    this.dom.children[this.selection].setAttribute('aria-selected', 'true');
   }
 
   /** @param key The key name as produced by translateKey(). */
@@ -305,6 +316,7 @@ export class ReadLine {
   inputBox = html('div', { className: 'input-box' });
   input = html('input', {
     spellcheck: false,
+    'aria-label': 'command',
   }) as HTMLInputElement;
 
   delegates = {
@@ -355,8 +367,12 @@ export class ReadLine {
     });
   }
 
-  setPrompt(text: string) {
-    this.prompt.innerText = `${text}$ `;
+  setPrompt(spans: PromptSpan[]) {
+    this.prompt.innerText = '';
+    for (const { text, color } of spans) {
+      const style = color ? { color: `var(--${color})` } : {};
+      this.prompt.appendChild(html('span', { style }, htext(text)));
+    }
   }
 
   setText(text: string) {
@@ -369,6 +385,15 @@ export class ReadLine {
     this.input.selectionStart = this.input.selectionEnd = pos;
   }
 
+  /** Inserts text at the cursor, replacing any selection. */
+  insertText(text: string) {
+    const { value, selectionStart, selectionEnd } = this.input;
+    const start = selectionStart ?? value.length;
+    const end = selectionEnd ?? start;
+    this.setText(value.substring(0, start) + text + value.substring(end));
+    this.setPos(start + text.length);
+  }
+
   showHistory(delta: -1 | 0 | 1) {
     switch (delta) {
       case -1: {
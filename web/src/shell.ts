@@ -1,16 +1,108 @@
 import { AliasMap } from './alias';
+import { Frecency } from './frecency';
+import { History } from './history';
 import * as path from './path';
+import { defaultPrompt, expandPrompt, PromptSpan } from './prompt';
+import * as theme from './theme';
 
-export function parseCmd(cmd: string): string[] {
-  const parts = cmd.trim().split(/\s+/);
-  if (parts.length === 1 && parts[0] === '') return [];
-  return parts;
+/**
+ * Splits a command line into words as sh does, handling single quotes, double
+ * quotes, and backslash escapes.  Outside of single quotes, $VAR and ${VAR}
+ * are expanded from env.
+ */
+export function parseCmd(
+  cmd: string,
+  env = new Map<string, string>()
+): string[] {
+  const words: string[] = [];
+  // The word being built, or undefined when between words.
+  let word: string | undefined;
+  let i = 0;
+
+  // Expands the variable reference at cmd[i], which is a '$'.
+  const expandVar = (): string => {
+    const m = /^\$(?:\{(\w+)\}|(\w+))/.exec(cmd.substring(i));
+    if (!m) {
+      i++;
+      return '$';
+    }
+    i += m[0].length;
+    return env.get(m[1] ?? m[2]) ?? '';
+  };
+
+  while (i < cmd.length) {
+    const c = cmd[i];
+    if (/\s/.test(c)) {
+      if (word !== undefined) words.push(word);
+      word = undefined;
+      i++;
+      continue;
+    }
+    word = word ?? '';
+    if (c === '\\') {
+      word += cmd[i + 1] ?? '';
+      i += 2;
+    } else if (c === "'") {
+      let end = cmd.indexOf("'", i + 1);
+      if (end === -1) end = cmd.length;
+      word += cmd.substring(i + 1, end);
+      i = end + 1;
+    } else if (c === '"') {
+      i++;
+      while (i < cmd.length && cmd[i] !== '"') {
+        if (cmd[i] === '\\' && /["\\$`]/.test(cmd[i + 1])) {
+          word += cmd[i + 1];
+          i += 2;
+        } else if (cmd[i] === '$') {
+          word += expandVar();
+        } else {
+          word += cmd[i++];
+        }
+      }
+      i++;
+    } else if (c === '$') {
+      word += expandVar();
+    } else {
+      word += c;
+      i++;
+    }
+  }
+  if (word !== undefined) words.push(word);
+  return words;
+}
+
+/** Quotes an argument, if needed, so the shell reads it as one word. */
+export function quote(arg: string): string {
+  if (/^[\w@%+=:,./-]+$/.test(arg)) return arg;
+  return `'${arg.replace(/'/g, `'\\''`)}'`;
+}
+
+/**
+ * Backslash-escapes the shell metacharacters in an argument.  Unlike quote(),
+ * the result starts with the same text as the argument does, so it suits
+ * completions that extend a partially typed word.
+ */
+export function escape(arg: string): string {
+  // A leading ~ is left alone, so completions like ~user/ still expand.
+  const tilde = arg.startsWith('~') ? '~' : '';
+  return (
+    tilde +
+    arg
+      .substring(tilde.length)
+      .replace(/[\s'"\\$`!*?[\]{}()<>|&;#~]/g, (c) => '\\' + c)
+  );
 }
 
 export interface ExecRemote {
   kind: 'remote';
   cwd: string;
   cmd: string[];
+  /** Called when the server reports the command changed directory. */
+  onChdir?: (dir: string) => void;
+  /** The duration after which the server kills the command, if any. */
+  timeout?: string;
+  /** The same duration, in milliseconds. */
+  timeoutMs?: number;
   onComplete?: (exitCode: number) => void;
 }
 
@@ -23,6 +115,8 @@ export interface TableOutput {
 export interface StringOutput {
   kind: 'string';
   output: string;
+  /** The exit status, if not 0, e.g. for a builtin reporting an error. */
+  status?: number;
 }
 
 export type ExecOutput = ExecRemote | TableOutput | StringOutput;
@@ -31,9 +125,47 @@ function strOutput(msg: string): ExecOutput {
   return { kind: 'string', output: msg };
 }
 
+/** An error from a builtin: status 2 for bad usage, as in sh, else 1. */
+function errOutput(msg: string): ExecOutput {
+  const status = msg.startsWith('usage:') ? 2 : 1;
+  return { kind: 'string', output: msg, status };
+}
+
+/**
+ * The variables that configure smash, each with a check of its value that
+ * returns a complaint, or '' if the value is fine.
+ */
+const settings = new Map<string, (val: string) => string>([
+  [
+    'SMASH_ANNOUNCE',
+    (val) =>
+      ['all', 'errors', 'off'].includes(val)
+        ? ''
+        : 'must be all, errors or off',
+  ],
+  [
+    'SMASH_EDITOR_URL',
+    (val) => (!val || val.includes('{file}') ? '' : 'must contain {file}'),
+  ],
+  ['SMASH_PROMPT', () => ''],
+  ['SMASH_SHELL', () => ''],
+]);
+
 export class Shell {
   aliases = new AliasMap();
+  /** Command history, shared with forks of this shell. */
+  history = new History();
+  /** Visited directories, for 'j'; also shared with forks. */
+  dirs = new Frecency();
   cwd = '/';
+  /** The previous cwd, for 'cd -'. */
+  oldCwd: string | undefined;
+  /** The pushd directory stack, not including the cwd. */
+  dirStack: string[] = [];
+  /** The exit status of the last command, as in $?. */
+  exitCode = 0;
+  /** The git branch of the cwd, as of the last command run by the server. */
+  gitBranch = '';
 
   constructor(public env = new Map<string, string>()) {}
 
@@ -42,74 +174,460 @@ export class Shell {
     this.aliases.set('that', `${this.env.get('SMASH')} that`);
   }
 
-  cwdForPrompt() {
-    let cwd = this.cwd;
+  /** Checks the SMASH_* settings in an export command's arguments. */
+  private checkSettings(argv: string[]): string[] {
+    if (argv[0] !== 'export') return [];
+    const errors: string[] = [];
+    for (const arg of argv.slice(1)) {
+      const [name, ...rest] = arg.split('=');
+      if (!name.startsWith('SMASH_') || rest.length === 0) continue;
+      const check = settings.get(name);
+      const err = check ? check(rest.join('=')) : 'unknown setting';
+      if (err) errors.push(`${name}: ${err}`);
+    }
+    return errors;
+  }
+
+  /**
+   * Runs the lines of an rc file, returning any errors.  Only builtins like
+   * alias and export can be used, as there is no cell to run commands in.
+   * As the rc file is smash's config file, exported SMASH_* settings are
+   * also checked for typos and bad values.
+   */
+  runRc(text: string): string[] {
+    const errors: string[] = [];
+    text.split('\n').forEach((line, i) => {
+      line = line.trim();
+      if (!line || line.startsWith('#')) return;
+      for (const err of this.checkSettings(parseCmd(line, this.env))) {
+        errors.push(`rc:${i + 1}: ${err}`);
+      }
+      const out = this.exec(line);
+      let err = '';
+      if (out.kind === 'remote') {
+        err = `${line}: only builtins can be used in rc files`;
+      } else if (out.kind === 'string' && out.status) {
+        err = out.output;
+      }
+      if (err) errors.push(`rc:${i + 1}: ${err}`);
+    });
+    return errors;
+  }
+
+  /**
+   * Completes a $VAR or ${VAR} reference ending at pos from the session env,
+   * or returns undefined if there isn't one there.
+   */
+  completeVar(
+    input: string,
+    pos: number
+  ): { completions: string[]; pos: number } | undefined {
+    const m = input.substring(0, pos).match(/\$(\{?)(\w*)$/);
+    if (!m) return;
+    const [ref, brace, prefix] = m;
+    const completions = Array.from(this.env.keys())
+      .filter((name) => name.startsWith(prefix))
+      .sort()
+      .map((name) => (brace ? `\${${name}}` : `$${name}`));
+    return { completions, pos: pos - ref.length };
+  }
+
+  /** Creates a new shell starting with a copy of this shell's state. */
+  fork(): Shell {
+    const shell = new Shell(new Map(this.env));
+    shell.aliases.replaceAll(new Map(this.aliases.aliases));
+    shell.history = this.history;
+    shell.dirs = this.dirs;
+    shell.cwd = this.cwd;
+    shell.gitBranch = this.gitBranch;
+    return shell;
+  }
+
+  /** Abbreviates a path under $HOME using '~'. */
+  tildify(dir: string): string {
     const home = this.env.get('HOME');
-    if (home && cwd.startsWith(home)) {
-      cwd = '~' + cwd.substring(home.length);
+    if (home && dir.startsWith(home)) {
+      dir = '~' + dir.substring(home.length);
     }
-    return cwd;
+    return dir;
+  }
+
+  cwdForPrompt() {
+    return this.tildify(this.cwd);
+  }
+
+  /** Returns the value of a {NAME} tag in the prompt template. */
+  private promptValue(name: string): string {
+    if (name.startsWith('$')) return this.env.get(name.substring(1)) ?? '';
+    switch (name) {
+      case 'cwd':
+        return this.cwdForPrompt();
+      case 'status':
+        return String(this.exitCode);
+      case 'user':
+        return this.env.get('USER') ?? '';
+      case 'host':
+        return this.env.get('HOSTNAME') ?? '';
+      case 'time':
+        return new Date().toTimeString().substring(0, 5);
+      case 'git':
+        return this.gitBranch;
+    }
+    return '';
+  }
+
+  /**
+   * Expands the prompt template from $SMASH_PROMPT, in the language described
+   * in prompt.ts.  The values available are cwd, status (of the last
+   * command), user, host, time (HH:MM), git (the branch), and $VAR.
+   */
+  prompt(): PromptSpan[] {
+    const template = this.env.get('SMASH_PROMPT') || defaultPrompt;
+    return expandPrompt(template, (name) => this.promptValue(name));
+  }
+
+  /** Resolves a directory argument against the cwd. */
+  private resolveDir(dir: string): string {
+    if (!dir.startsWith('/')) {
+      dir = path.join(this.cwd, dir);
+    }
+    dir = path.normalize(dir);
+    if (dir.length > 1 && dir.endsWith('/')) {
+      dir = dir.substring(0, dir.length - 1);
+    }
+    return dir;
+  }
+
+  /**
+   * Returns the directories to try for a cd argument, in order.  As in bash,
+   * relative paths other than ./foo and ../foo are searched for in $CDPATH,
+   * where an empty entry means the cwd.
+   */
+  cdCandidates(arg: string): string[] {
+    const cdpath = this.env.get('CDPATH');
+    if (!cdpath || arg.startsWith('/') || /^\.\.?(\/|$)/.test(arg)) {
+      return [this.resolveDir(arg)];
+    }
+    const dirs = cdpath
+      .split(':')
+      .map((entry) => this.resolveDir(path.join(entry || '.', arg)));
+    const local = this.resolveDir(arg);
+    if (!dirs.includes(local)) dirs.push(local);
+    return dirs;
+  }
+
+  /**
+   * Changes to the first existing directory among dirs, as checked by the
+   * server, calling onChange with the previous cwd on success.
+   */
+  private chdir(dirs: string[], onChange?: (prev: string) => void): ExecOutput {
+    const prev = this.cwd;
+    let chosen: string | undefined;
+    return {
+      kind: 'remote',
+      cwd: this.cwd,
+      cmd: ['cd', ...dirs],
+      onChdir: (dir: string) => {
+        chosen = dir;
+      },
+      onComplete: (exitCode: number) => {
+        if (exitCode !== 0) return;
+        this.cwd = chosen ?? dirs[0];
+        this.oldCwd = prev;
+        this.dirs.visit(this.cwd);
+        onChange?.(prev);
+      },
+    };
   }
 
   builtinCd(argv: string[]): ExecOutput {
     if (argv.length > 1) {
-      return strOutput('usage: cd [DIR]');
+      return errOutput('usage: cd [DIR]');
     }
     let arg = argv[0];
     if (!arg) {
       arg = this.env.get('HOME') || '/';
     }
-    if (!arg.startsWith('/')) {
-      arg = path.join(this.cwd, arg);
+    if (arg === '-') {
+      if (!this.oldCwd) return errOutput('cd: no previous directory');
+      return this.chdir([this.oldCwd]);
+    }
+    return this.chdir(this.cdCandidates(arg));
+  }
+
+  /** Jumps to the best-ranked visited directory matching the fragments. */
+  builtinJ(argv: string[]): ExecOutput {
+    if (argv.length === 0) {
+      return {
+        kind: 'table',
+        headers: ['dir', 'score'],
+        rows: this.dirs
+          .match([])
+          .map((dir) => [this.tildify(dir), this.dirs.score(dir).toFixed(1)]),
+      };
+    }
+    const best = this.dirs.match(argv)[0];
+    if (!best) return errOutput(`j: no match for ${argv.join(' ')}`);
+    return this.chdir([best]);
+  }
+
+  builtinPushd(argv: string[]): ExecOutput {
+    if (argv.length > 1) {
+      return errOutput('usage: pushd [DIR]');
+    }
+    if (argv.length === 0) {
+      // Swap the top two directories.
+      const top = this.dirStack[0];
+      if (!top) return errOutput('pushd: no other directory');
+      return this.chdir([top], (prev) => {
+        this.dirStack[0] = prev;
+      });
+    }
+    return this.chdir(this.cdCandidates(argv[0]), (prev) => {
+      this.dirStack.unshift(prev);
+    });
+  }
+
+  builtinPopd(argv: string[]): ExecOutput {
+    if (argv.length > 0) {
+      return errOutput('usage: popd');
+    }
+    const top = this.dirStack[0];
+    if (!top) return errOutput('popd: directory stack empty');
+    return this.chdir([top], () => {
+      this.dirStack.shift();
+    });
+  }
+
+  builtinDirs(): ExecOutput {
+    return strOutput(
+      [this.cwd, ...this.dirStack].map((dir) => this.tildify(dir)).join(' ')
+    );
+  }
+
+  builtinAlias(argv: string[]): ExecOutput {
+    if (argv.length === 0) {
+      return {
+        kind: 'table',
+        headers: ['alias', 'expansion'],
+        rows: Array.from(this.aliases.aliases),
+      };
+    }
+    const defs: string[] = [];
+    for (const arg of argv) {
+      const m = arg.match(/^([^\s=]+)=(.*)$/);
+      if (!m) {
+        const exp = this.aliases.aliases.get(arg);
+        if (exp === undefined) return errOutput(`alias: ${arg}: not found`);
+        defs.push(`alias ${arg}=${quote(exp)}`);
+        continue;
+      }
+      this.aliases.set(m[1], m[2]);
+    }
+    return strOutput(defs.join('\n'));
+  }
+
+  builtinUnalias(argv: string[]): ExecOutput {
+    if (argv.length === 0) {
+      return errOutput('usage: unalias -a | NAME...');
+    }
+    if (argv[0] === '-a') {
+      this.aliases.replaceAll(new Map());
+      return strOutput('');
     }
-    arg = path.normalize(arg);
-    if (arg.length > 1 && arg.endsWith('/')) {
-      arg = arg.substring(0, arg.length - 1);
+    for (const name of argv) {
+      if (!this.aliases.remove(name)) {
+        return errOutput(`unalias: ${name}: not found`);
+      }
     }
+    return strOutput('');
+  }
+
+  private envTable(): ExecOutput {
     return {
-      kind: 'remote',
-      cwd: this.cwd,
-      cmd: ['cd', arg],
-      onComplete: (exitCode: number) => {
-        if (exitCode === 0) {
-          this.cwd = arg;
-        }
-      },
+      kind: 'table',
+      headers: ['var', 'value'],
+      rows: Array.from(this.env),
     };
   }
 
+  builtinExport(argv: string[]): ExecOutput {
+    if (argv.length === 0) return this.envTable();
+    for (const arg of argv) {
+      const eq = arg.indexOf('=');
+      // "export NAME" without a value is a no-op, as there are no unexported
+      // shell variables to promote.
+      if (eq === -1) continue;
+      const name = arg.substring(0, eq);
+      if (!/^[A-Za-z_]\w*$/.test(name)) {
+        return errOutput(`export: bad variable name: ${name}`);
+      }
+      this.env.set(name, arg.substring(eq + 1));
+    }
+    return strOutput('');
+  }
+
+  builtinUnset(argv: string[]): ExecOutput {
+    if (argv.length === 0) {
+      return errOutput('usage: unset NAME...');
+    }
+    for (const name of argv) {
+      this.env.delete(name);
+    }
+    return strOutput('');
+  }
+
+  builtinHistory(argv: string[]): ExecOutput {
+    const usage = errOutput('usage: history [-d N | search TEXT]');
+    const table = (filter: (cmd: string) => boolean): ExecOutput => ({
+      kind: 'table',
+      headers: ['#', 'command'],
+      rows: this.history
+        .list()
+        .map((cmd, i) => [String(i + 1), cmd])
+        .filter(([, cmd]) => filter(cmd)),
+    });
+    switch (argv[0]) {
+      case undefined:
+        return table(() => true);
+      case '-d':
+        if (argv.length !== 2) return usage;
+        if (!this.history.remove(Number(argv[1]) - 1)) {
+          return errOutput(`history: ${argv[1]}: no such entry`);
+        }
+        return strOutput('');
+      case 'search': {
+        if (argv.length < 2) return usage;
+        const text = argv.slice(1).join(' ');
+        return table((cmd) => cmd.includes(text));
+      }
+      default:
+        return usage;
+    }
+  }
+
+  builtinTheme(argv: string[]): ExecOutput {
+    // 'theme list' and 'theme set NAME' are long forms of 'theme [NAME]'.
+    if (argv[0] === 'list' && argv.length === 1) {
+      argv = [];
+    } else if (argv[0] === 'set' && argv.length === 2) {
+      argv = argv.slice(1);
+    }
+    if (argv.length > 1) {
+      return errOutput('usage: theme [list | [set] NAME | auto]');
+    }
+    if (argv.length === 0) {
+      return {
+        kind: 'table',
+        headers: ['theme', ''],
+        rows: [
+          ...Array.from(theme.themes.keys()).map((name) => [
+            name,
+            name === theme.current ? 'current' : '',
+          ]),
+          ['auto', theme.auto ? 'on' : 'off'],
+        ],
+      };
+    }
+    if (!theme.setTheme(argv[0])) {
+      return errOutput(`theme: no such theme: ${argv[0]}`);
+    }
+    return strOutput('');
+  }
+
+  builtinSpacing(argv: string[]): ExecOutput {
+    if (argv.length === 0) {
+      return {
+        kind: 'table',
+        headers: ['spacing', ''],
+        rows: theme.spacingVars.map((name) => [
+          name,
+          theme.spacing.get(name) ?? '',
+        ]),
+      };
+    }
+    if (argv.length > 2) {
+      return errOutput('usage: spacing [NAME [VALUE]]');
+    }
+    if (!theme.setSpacing(argv[0], argv[1] ?? '')) {
+      return errOutput(`spacing: no such variable: ${argv[0]}`);
+    }
+    return strOutput('');
+  }
+
   private handleBuiltin(argv: string[]): ExecOutput | undefined {
     switch (argv[0]) {
       case 'alias':
-        if (argv.length > 2) {
-          return strOutput('usage: alias [CMD]');
-        }
-        if (argv.length > 1) {
-          return strOutput('TODO: alias CMD');
-        }
-        return {
-          kind: 'table',
-          headers: ['alias', 'expansion'],
-          rows: Array.from(this.aliases.aliases),
-        };
+        return this.builtinAlias(argv.slice(1));
       case 'cd':
         return this.builtinCd(argv.slice(1));
+      case 'dirs':
+        return this.builtinDirs();
+      case 'popd':
+        return this.builtinPopd(argv.slice(1));
+      case 'pushd':
+        return this.builtinPushd(argv.slice(1));
       case 'env':
         if (argv.length > 1) return;
-        return {
-          kind: 'table',
-          headers: ['var', 'value'],
-          rows: Array.from(this.env),
-        };
+        return this.envTable();
+      case 'export':
+        return this.builtinExport(argv.slice(1));
+      case 'history':
+        return this.builtinHistory(argv.slice(1));
+      case 'j':
+        return this.builtinJ(argv.slice(1));
+      case 'spacing':
+        return this.builtinSpacing(argv.slice(1));
+      case 'theme':
+        return this.builtinTheme(argv.slice(1));
+      case 'unalias':
+        return this.builtinUnalias(argv.slice(1));
+      case 'unset':
+        return this.builtinUnset(argv.slice(1));
     }
   }
 
+  /**
+   * Runs a command with a deadline, after which the server kills the
+   * command's process group, e.g. "timeout: 30s curl ...".  Durations are
+   * as for timeout(1): a number with an optional s, m, h, or d suffix.
+   */
+  private execWithTimeout(duration: string, cmd: string): ExecOutput {
+    const m = duration.match(/^(\d+(?:\.\d+)?)([smhd]?)$/);
+    if (!m) {
+      return errOutput(`timeout: bad duration: ${duration}`);
+    }
+    const units: { [suffix: string]: number } = { m: 60, h: 3600, d: 86400 };
+    const timeoutMs = Math.round(Number(m[1]) * (units[m[2]] ?? 1) * 1000);
+    const out = this.exec(cmd);
+    // Builtins complete immediately, and cd is run by the server itself.
+    if (out.kind !== 'remote' || out.cmd[0] === 'cd') return out;
+    return { ...out, timeout: duration, timeoutMs };
+  }
+
   exec(cmd: string): ExecOutput {
     cmd = cmd.trim();
+    const timeout = cmd.match(/^timeout:\s*(\S+)\s+(.*)$/);
+    if (timeout) return this.execWithTimeout(timeout[1], timeout[2]);
     cmd = this.aliases.expand(cmd);
-    const argv = parseCmd(cmd);
+    const argv = parseCmd(cmd, this.env);
     const out = this.handleBuiltin(argv);
     if (out) return out;
-    return { kind: 'remote', cwd: this.cwd, cmd: ['/bin/sh', '-c', cmd] };
+    // Non-builtin commands are run by the shell named in $SMASH_SHELL, so
+    // e.g. SMASH_SHELL=/bin/zsh allows zsh syntax.
+    const sh = this.env.get('SMASH_SHELL') || '/bin/sh';
+    return {
+      kind: 'remote',
+      cwd: this.cwd,
+      cmd: [sh, '-c', cmd],
+      // Programs can report their directory via OSC 7, e.g. when running a
+      // nested shell; the last one reported becomes the cwd.
+      onChdir: (dir: string) => {
+        if (dir === this.cwd) return;
+        this.oldCwd = this.cwd;
+        this.cwd = dir;
+        this.dirs.visit(dir);
+      },
+    };
   }
 }
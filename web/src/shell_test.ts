@@ -1,4 +1,4 @@
-import { Shell, ExecOutput, parseCmd } from './shell';
+import { Shell, ExecOutput, escape, parseCmd, quote } from './shell';
 import { expect } from 'chai';
 
 async function fakeExec(out: ExecOutput): Promise<void> {
@@ -23,6 +23,39 @@ describe('shell', async function () {
       expect(parseCmd('cd ')).deep.equal(['cd']);
       expect(parseCmd('cd  foo/bar   zz')).deep.equal(['cd', 'foo/bar', 'zz']);
     });
+
+    it('handles quotes and escapes', function () {
+      expect(parseCmd(`a 'b c' "d e" f\\ g`)).deep.equal([
+        'a',
+        'b c',
+        'd e',
+        'f g',
+      ]);
+      expect(parseCmd(`'a'"b"c`)).deep.equal(['abc']);
+      expect(parseCmd(`"a\\"b" 'a\\b'`)).deep.equal(['a"b', 'a\\b']);
+    });
+
+    it('expands variables', function () {
+      expect(parseCmd('cd $HOME/x', env)).deep.equal(['cd', '/home/evmar/x']);
+      expect(parseCmd(`"\${HOME}" '$HOME' \\$HOME`, env)).deep.equal([
+        '/home/evmar',
+        '$HOME',
+        '$HOME',
+      ]);
+    });
+  });
+
+  it('escapes arguments', function () {
+    expect(escape('foo/bar.txt')).equal('foo/bar.txt');
+    expect(escape("my file's (1)")).equal("my\\ file\\'s\\ \\(1\\)");
+    expect(escape('~user/a~b')).equal('~user/a\\~b');
+  });
+
+  it('quotes arguments', function () {
+    expect(quote('foo/bar.txt')).equal('foo/bar.txt');
+    expect(quote('a b')).equal("'a b'");
+    expect(quote("it's")).equal(`'it'\\''s'`);
+    expect(quote('')).equal("''");
   });
 
   it('elides homedir', function () {
@@ -33,6 +66,195 @@ describe('shell', async function () {
     expect(sh.cwdForPrompt()).equal('~/test');
   });
 
+  it('shows failed exit status in prompt', function () {
+    const sh = new Shell(new Map(env));
+    sh.cwd = '/home/evmar/test';
+    const prompt = () =>
+      sh
+        .prompt()
+        .map((span) => span.text)
+        .join('');
+    expect(prompt()).equal('~/test$ ');
+    sh.exitCode = 130;
+    expect(prompt()).equal('[130] ~/test$ ');
+    sh.env.set('SMASH_PROMPT', '{$HOME} {status}> ');
+    expect(prompt()).equal('/home/evmar 130> ');
+    sh.env.set('SMASH_PROMPT', '{cwd}{if git} ({git}){end}$ ');
+    expect(prompt()).equal('~/test$ ');
+    sh.gitBranch = 'main';
+    expect(prompt()).equal('~/test (main)$ ');
+  });
+
+  it('lists themes', function () {
+    const sh = new Shell(env);
+    const out = sh.exec('theme');
+    if (out.kind !== 'table') throw new Error('expected table');
+    expect(out.rows).deep.include(['light', 'current']);
+    expect(out.rows).deep.include(['dark', '']);
+    expect(out.rows).deep.include(['auto', 'on']);
+  });
+
+  it('lists spacing', function () {
+    const sh = new Shell(env);
+    const out = sh.exec('spacing');
+    if (out.kind !== 'table') throw new Error('expected table');
+    expect(out.rows).deep.include(['term-line-height', '']);
+    expect(sh.exec('spacing nope 1')).deep.equal({
+      kind: 'string',
+      output: 'spacing: no such variable: nope',
+      status: 1,
+    });
+  });
+
+  it('runs commands with $SMASH_SHELL', function () {
+    const sh = new Shell(new Map());
+    expect(sh.exec('ls | wc')).deep.include({
+      cmd: ['/bin/sh', '-c', 'ls | wc'],
+    });
+    sh.env.set('SMASH_SHELL', '/bin/zsh');
+    expect(sh.exec('ls')).deep.include({ cmd: ['/bin/zsh', '-c', 'ls'] });
+  });
+
+  it('follows directories reported by commands', function () {
+    const sh = new Shell(new Map());
+    sh.cwd = '/a';
+    const out = sh.exec('bash');
+    if (out.kind !== 'remote') throw new Error('expected remote');
+    out.onChdir?.('/b');
+    expect(sh.cwd).equal('/b');
+    expect(sh.oldCwd).equal('/a');
+  });
+
+  it('runs rc files', function () {
+    const sh = new Shell(new Map());
+    const errors = sh.runRc(
+      ['# comment', "alias ll='ls -l'", '', 'export FOO=bar', 'ls'].join('\n')
+    );
+    expect(sh.aliases.expand('ll')).equal('ls -l');
+    expect(sh.env.get('FOO')).equal('bar');
+    expect(errors).deep.equal([
+      'rc:5: ls: only builtins can be used in rc files',
+    ]);
+  });
+
+  it('checks settings in rc files', function () {
+    const sh = new Shell(new Map());
+    const errors = sh.runRc(
+      [
+        'export SMASH_PROMPT=">" SMASH_ANOUNCE=off',
+        'export SMASH_ANNOUNCE=some',
+        'export OTHER=x',
+      ].join('\n')
+    );
+    expect(errors).deep.equal([
+      'rc:1: SMASH_ANOUNCE: unknown setting',
+      'rc:2: SMASH_ANNOUNCE: must be all, errors or off',
+    ]);
+    expect(sh.env.get('SMASH_PROMPT')).equal('>');
+  });
+
+  it('lists, searches, and deletes history', function () {
+    const sh = new Shell(new Map());
+    sh.history.add('ls');
+    sh.history.add('make test');
+    sh.history.add('make');
+    expect(sh.exec('history search make')).deep.include({
+      rows: [
+        ['2', 'make test'],
+        ['3', 'make'],
+      ],
+    });
+    sh.exec('history -d 2');
+    expect(sh.history.list()).deep.equal(['ls', 'make']);
+    expect(sh.exec('history -d 5')).deep.equal({
+      kind: 'string',
+      output: 'history: 5: no such entry',
+      status: 1,
+    });
+  });
+
+  it('runs commands with a timeout', function () {
+    const sh = new Shell(new Map());
+    expect(sh.exec('timeout: 30s curl x')).deep.include({
+      cmd: ['/bin/sh', '-c', 'curl x'],
+      timeout: '30s',
+      timeoutMs: 30000,
+    });
+    expect(sh.exec('timeout: soon curl x')).deep.equal({
+      kind: 'string',
+      output: 'timeout: bad duration: soon',
+      status: 1,
+    });
+  });
+
+  it('completes variables', function () {
+    const sh = new Shell(
+      new Map([
+        ['HOME', '/h'],
+        ['HOSTNAME', 'x'],
+        ['PATH', '/bin'],
+      ])
+    );
+    expect(sh.completeVar('echo $HO', 8)).deep.equal({
+      completions: ['$HOME', '$HOSTNAME'],
+      pos: 5,
+    });
+    expect(sh.completeVar('cd ${PA', 7)).deep.equal({
+      completions: ['${PATH}'],
+      pos: 3,
+    });
+    expect(sh.completeVar('cd PA', 5)).equal(undefined);
+  });
+
+  it('jumps to visited directories', async function () {
+    const sh = new Shell(new Map());
+    await fakeExec(sh.exec('cd /home/a/src/smash'));
+    await fakeExec(sh.exec('cd /tmp'));
+    await fakeExec(sh.exec('j sma'));
+    expect(sh.cwd).equal('/home/a/src/smash');
+    expect(sh.exec('j nope')).deep.equal({
+      kind: 'string',
+      output: 'j: no match for nope',
+      status: 1,
+    });
+  });
+
+  describe('alias', function () {
+    it('defines and removes aliases', function () {
+      const sh = new Shell(new Map());
+      sh.exec("alias ll='ls -l'");
+      expect(sh.aliases.aliases.get('ll')).equal('ls -l');
+      expect(sh.exec('alias ll')).deep.equal({
+        kind: 'string',
+        output: "alias ll='ls -l'",
+      });
+      sh.exec('unalias ll');
+      expect(sh.aliases.aliases.has('ll')).equal(false);
+    });
+  });
+
+  describe('env', function () {
+    it('exports and unsets', function () {
+      const sh = new Shell(new Map());
+      sh.exec('export FOO=bar BAZ=a=b');
+      expect(sh.env.get('FOO')).equal('bar');
+      expect(sh.env.get('BAZ')).equal('a=b');
+      sh.exec('unset FOO');
+      expect(sh.env.has('FOO')).equal(false);
+    });
+
+    it('rejects bad names', function () {
+      const sh = new Shell(new Map());
+      const out = sh.exec('export 1X=y');
+      expect(out).deep.equal({
+        kind: 'string',
+        output: 'export: bad variable name: 1X',
+        status: 1,
+      });
+      expect(sh.env.size).equal(0);
+    });
+  });
+
   describe('cd', function () {
     it('goes home', async function () {
       const sh = new Shell(env);
@@ -46,5 +268,48 @@ describe('shell', async function () {
       await fakeExec(sh.builtinCd(['foo//bar/']));
       expect(sh.cwd).equal('/home/evmar/foo/bar');
     });
+
+    it('goes back with -', async function () {
+      const sh = new Shell(env);
+      await fakeExec(sh.builtinCd(['/tmp']));
+      await fakeExec(sh.builtinCd(['/usr']));
+      await fakeExec(sh.builtinCd(['-']));
+      expect(sh.cwd).equal('/tmp');
+    });
+
+    it('searches CDPATH', function () {
+      const sh = new Shell(new Map([['CDPATH', '/src:']]));
+      sh.cwd = '/home';
+      expect(sh.cdCandidates('foo')).deep.equal(['/src/foo', '/home/foo']);
+      expect(sh.cdCandidates('./foo')).deep.equal(['/home/foo']);
+      expect(sh.cdCandidates('/foo')).deep.equal(['/foo']);
+    });
+
+    it('goes where the server found the directory', function () {
+      const sh = new Shell(new Map([['CDPATH', '/src:']]));
+      sh.cwd = '/home';
+      const out = sh.exec('cd foo');
+      if (out.kind !== 'remote') throw new Error('expected remote');
+      expect(out.cmd).deep.equal(['cd', '/src/foo', '/home/foo']);
+      out.onChdir!('/home/foo');
+      out.onComplete!(0);
+      expect(sh.cwd).equal('/home/foo');
+    });
+  });
+
+  describe('pushd', function () {
+    it('pushes and pops', async function () {
+      const sh = new Shell(env);
+      sh.cwd = '/a';
+      await fakeExec(sh.builtinPushd(['/b']));
+      await fakeExec(sh.builtinPushd(['/c']));
+      expect(sh.dirStack).deep.equal(['/b', '/a']);
+      await fakeExec(sh.builtinPushd([]));
+      expect(sh.cwd).equal('/b');
+      expect(sh.dirStack).deep.equal(['/c', '/a']);
+      await fakeExec(sh.builtinPopd([]));
+      expect(sh.cwd).equal('/c');
+      expect(sh.dirStack).deep.equal(['/a']);
+    });
   });
 });
@@ -1,21 +1,48 @@
 import { ServerConnection } from './connection';
-import { Shell } from './shell';
+import { History } from './history';
+import { quote, Shell } from './shell';
+import { statusBar } from './statusbar';
 import { Tabs } from './tabs';
+import * as theme from './theme';
 import { html, htext } from './html';
+import { translateKey } from './readline';
 
 const tabs = new Tabs();
 
+/** Command history, kept across reconnects. */
+const history = new History();
+
 async function connect() {
   const conn = new ServerConnection();
   const hello = await conn.connect();
 
   const shell = new Shell();
+  shell.history = history;
   shell.aliases.replaceAll(
     new Map<string, string>(hello.alias.map(({ key, val }) => [key, val]))
   );
   shell.env = new Map(hello.env.map(({ key, val }) => [key, val]));
   shell.init();
-  tabs.addCells(shell);
+  const errors = hello.errors.slice();
+  // Load themes first, so the rc file can select one.
+  for (const { key: file, val } of hello.themes) {
+    const imported = theme.importTheme(val);
+    if (!imported) {
+      errors.push(`${file}: unrecognized theme format`);
+      continue;
+    }
+    // Themes are named by file name, without extension or leading dot, so
+    // ~/.Xresources becomes 'Xresources'.
+    const name = file
+      .replace(/^.*\//, '')
+      .replace(/^\./, '')
+      .replace(/\.[^.]*$/, '');
+    theme.themes.set(name, imported);
+  }
+  errors.push(...shell.runRc(hello.rc));
+  // Show errors in the new tab; on reconnect, the selected tab is a dead one.
+  const panes = tabs.addCells(shell);
+  if (errors.length > 0) panes.focused.addError(errors.join('\n'));
   tabs.focus();
 
   tabs.delegates = {
@@ -58,12 +85,49 @@ async function reconnectPrompt(message: string) {
   document.body.removeChild(dom);
 }
 
+/**
+ * Converts dropped data to text for the prompt: dropped files become
+ * shell-quoted paths, and anything else is pasted as text.
+ */
+function droppedText(data: DataTransfer): string {
+  const uris = data
+    .getData('text/uri-list')
+    .split(/\r?\n/)
+    .filter((uri) => uri.startsWith('file://'));
+  if (uris.length > 0) {
+    return uris
+      .map((uri) => quote(decodeURIComponent(new URL(uri).pathname)))
+      .join(' ');
+  }
+  return data.getData('text/plain');
+}
+
+function setPresentation(on: boolean) {
+  document.body.classList.toggle('presentation', on);
+  theme.invalidateCellSize();
+}
+
+/** Toggles presentation mode: fullscreen, larger text, and no chrome. */
+function togglePresentation() {
+  if (!document.body.classList.contains('presentation')) {
+    setPresentation(true);
+    document.documentElement.requestFullscreen().catch(() => {});
+  } else if (document.fullscreenElement) {
+    // Ends presentation mode via the fullscreenchange handler.
+    document.exitFullscreen();
+  } else {
+    setPresentation(false);
+  }
+}
+
 async function main() {
   // Register an unused service worker so 'add to homescreen' works.
   // TODO: even when we do this, we still get a URL bar?!
   // await navigator.serviceWorker.register('worker.js');
 
+  theme.watchSystemTheme();
   document.body.appendChild(tabs.dom);
+  document.body.appendChild(statusBar.dom);
 
   // Clicking on the page, if it tries to focus the document body,
   // should redirect focus to the relevant place in the cell stack.
@@ -75,6 +139,27 @@ async function main() {
     }
   });
 
+  document.addEventListener('dragover', (ev) => {
+    ev.preventDefault();
+  });
+  document.addEventListener('drop', (ev) => {
+    ev.preventDefault();
+    if (!ev.dataTransfer) return;
+    tabs.insertText(droppedText(ev.dataTransfer));
+  });
+
+  // Leaving fullscreen, e.g. with Esc, also leaves presentation mode.
+  document.addEventListener('fullscreenchange', () => {
+    if (!document.fullscreenElement) setPresentation(false);
+  });
+  document.addEventListener('keydown', (ev) => {
+    if (ev.defaultPrevented) return;
+    if (translateKey(ev) === 'C-P') {
+      togglePresentation();
+      ev.preventDefault();
+    }
+  });
+
   for (;;) {
     try {
       const conn = await connect();
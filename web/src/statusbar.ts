@@ -0,0 +1,47 @@
+import { html } from './html';
+
+/**
+ * The bar along the bottom of the window, showing session information and
+ * transient messages.
+ */
+export class StatusBar {
+  info = html('div', { className: 'status-info' });
+  message = html('div', { className: 'status-message' });
+  dom = html('div', { className: 'statusbar' }, this.info, this.message);
+  private messageTimer: number | undefined;
+
+  /** Updates the persistent session information. */
+  setInfo(cwd: string, running: number) {
+    let text = cwd;
+    if (running > 0) text += ` · ${running} running`;
+    this.info.innerText = text;
+  }
+
+  /** Shows a message that disappears after a few seconds. */
+  showMessage(text: string, ms = 3000) {
+    this.message.innerText = text;
+    window.clearTimeout(this.messageTimer);
+    this.messageTimer = window.setTimeout(() => {
+      this.message.innerText = '';
+    }, ms);
+  }
+}
+
+export const statusBar = new StatusBar();
+
+/** Offscreen live region, for announcing events to screen readers. */
+const liveRegion = html('div', {
+  className: 'screenreader-only',
+  'aria-live': 'polite',
+});
+
+/** Announces a message to screen readers without showing it. */
+export function announce(text: string) {
+  if (!liveRegion.parentNode) document.body.appendChild(liveRegion);
+  // Screen readers only announce changes, so clear the region first and set
+  // the text separately, for repeated messages to be announced again.
+  liveRegion.innerText = '';
+  window.setTimeout(() => {
+    liveRegion.innerText = text;
+  }, 100);
+}
@@ -1,14 +1,16 @@
-import { CellStack } from './cells';
 import { html, htext } from './html';
+import { Panes } from './panes';
 import * as proto from './proto';
+import { translateKey } from './readline';
 import { Shell } from './shell';
+import { statusBar } from './statusbar';
 
 interface Tab {
   /** The tab widget itself, as shown in the tab strip. */
   dom: HTMLElement;
 
   /** The contents of the tab, shown when the tab is selected. */
-  cellStack: CellStack;
+  panes: Panes;
 }
 
 export class Tabs {
@@ -21,42 +23,129 @@ export class Tabs {
     send: (msg: proto.ClientMessage) => {},
   };
 
-  addCells(shell: Shell) {
+  constructor() {
+    this.dom.addEventListener('keydown', (ev) => this.onKeyDown(ev));
+    this.dom.addEventListener('focusin', () => this.updateStatus());
+  }
+
+  private onKeyDown(ev: KeyboardEvent) {
+    if (ev.defaultPrevented) return;
+    switch (translateKey(ev)) {
+      case 'C-T':
+        this.addCells(this.tabs[this.sel].panes.focused.shell.fork());
+        this.showTab(this.tabs.length - 1);
+        this.focus();
+        break;
+      case 'C-W':
+        this.closeTab(this.sel);
+        break;
+      case 'C-PageUp':
+        this.showTab((this.sel + this.tabs.length - 1) % this.tabs.length);
+        this.focus();
+        break;
+      case 'C-PageDown':
+        this.showTab((this.sel + 1) % this.tabs.length);
+        this.focus();
+        break;
+      default:
+        return;
+    }
+    ev.preventDefault();
+  }
+
+  /** Adds a tab running the given shell, returning its panes. */
+  addCells(shell: Shell): Panes {
     const tab = this.newTab(shell);
     this.tabs.push(tab);
     this.tabStrip.appendChild(tab.dom);
-
-    if (this.tabs.length > 1) {
-      this.tabStrip.style.display = 'flex';
-    }
+    this.updateTabStrip();
 
     if (this.sel === -1) {
       this.showTab(0);
     }
+    return tab.panes;
   }
 
   private newTab(shell: Shell): Tab {
-    const dom = html('div', { className: 'tab' }, htext('tab'));
-    const cellStack = new CellStack(shell);
-    cellStack.delegates = {
+    const dom = html('div', { className: 'tab' }, htext(shell.cwdForPrompt()));
+    const panes = new Panes(shell);
+    panes.delegates = {
       send: (msg) => this.delegates.send(msg),
     };
-    return { dom, cellStack };
+    const tab = { dom, panes };
+    dom.onclick = () => {
+      this.showTab(this.tabs.indexOf(tab));
+      this.focus();
+    };
+    return tab;
+  }
+
+  /** Closes a tab, unless it is the only one or has a command running. */
+  closeTab(index: number) {
+    if (this.tabs.length === 1) {
+      statusBar.showMessage("can't close the last tab");
+      return;
+    }
+    if (this.tabs[index].panes.stacks().some((stack) => stack.isRunning())) {
+      statusBar.showMessage("can't close a tab with a running command");
+      return;
+    }
+    if (index === this.sel) {
+      this.showTab(index > 0 ? index - 1 : 1);
+    }
+    const [tab] = this.tabs.splice(index, 1);
+    this.tabStrip.removeChild(tab.dom);
+    if (this.sel > index) this.sel--;
+    this.updateTabStrip();
+    this.focus();
+  }
+
+  /** Shows the tab strip only when there is more than one tab. */
+  private updateTabStrip() {
+    this.tabStrip.style.display = this.tabs.length > 1 ? 'flex' : 'none';
   }
 
   handleMessage(msg: proto.ServerMsg): boolean {
-    const cellStack = this.tabs[0].cellStack;
     switch (msg.tag) {
       case 'CompleteResponse':
-        cellStack.getLastCell().onCompleteResponse(msg.val);
+        // Completion requests are identified by the id of the requesting cell.
+        this.findStack(msg.val.id)
+          ?.findCell(msg.val.id)
+          ?.onCompleteResponse(msg.val);
         return true;
       case 'CellOutput':
-        cellStack.onOutput(msg.val);
+        this.findStack(msg.val.cell)?.onOutput(msg.val);
+        this.updateStatus();
         return true;
     }
     return false;
   }
 
+  /** Refreshes the status bar from the focused cell stack. */
+  updateStatus() {
+    if (this.sel < 0) return;
+    let running = 0;
+    for (const tab of this.tabs) {
+      for (const stack of tab.panes.stacks()) {
+        running += stack.cells.filter((cell) => cell.running).length;
+      }
+    }
+    for (const tab of this.tabs) {
+      // Tabs are labelled with the cwd of their focused pane.
+      tab.dom.innerText = tab.panes.focused.shell.cwdForPrompt();
+    }
+    const shell = this.tabs[this.sel].panes.focused.shell;
+    statusBar.setInfo(shell.cwdForPrompt(), running);
+  }
+
+  /** Finds the cell stack, in any tab, containing the given cell id. */
+  private findStack(cellId: number) {
+    for (const tab of this.tabs) {
+      const stack = tab.panes.findStack(cellId);
+      if (stack) return stack;
+    }
+  }
+
   showTab(index: number) {
     if (this.sel === index) return;
     if (this.sel >= 0) {
@@ -65,10 +154,18 @@ export class Tabs {
     }
     this.sel = index;
     this.tabs[index].dom.style.position = 'relative';
-    this.dom.appendChild(this.tabs[index].cellStack.dom);
+    this.dom.appendChild(this.tabs[index].panes.dom);
   }
 
   focus() {
-    this.tabs[this.sel].cellStack.focus();
+    this.tabs[this.sel].panes.focus();
+  }
+
+  /** Inserts text into the focused prompt, as if typed. */
+  insertText(text: string) {
+    const cell = this.tabs[this.sel].panes.focused.getLastCell();
+    if (cell.running) return;
+    cell.readline.insertText(text);
+    cell.readline.focus();
   }
 }
@@ -1,6 +1,7 @@
 import { html, htext } from './html';
 import * as proto from './proto';
 import { translateKey } from './readline';
+import * as theme from './theme';
 
 interface Attr {
   fg: number;
@@ -29,6 +30,52 @@ const termKeyMap: { [key: string]: string } = {
   Escape: '\x1b',
 };
 
+/** A reference to a source location, like "src/foo.rs:12:5". */
+export interface FileRef {
+  /** Offset of the reference within the searched text. */
+  index: number;
+  /** The full text of the reference. */
+  text: string;
+  path: string;
+  line: number;
+  col?: number;
+}
+
+/** Finds compiler-style path:line[:col] references within a line of text. */
+export function findFileRefs(text: string): FileRef[] {
+  const refs: FileRef[] = [];
+  const re = /(?<![\w.\/~+:-])([\w.\/~+-]*[\w-]\.\w+):(\d+)(?::(\d+))?/g;
+  for (let m; (m = re.exec(text)); ) {
+    refs.push({
+      index: m.index,
+      text: m[0],
+      path: m[1],
+      line: Number(m[2]),
+      col: m[3] ? Number(m[3]) : undefined,
+    });
+  }
+  return refs;
+}
+
+/** Appends text to a parent node, wrapping any file references in spans. */
+function appendLinkified(parent: HTMLElement, text: string) {
+  let ofs = 0;
+  for (const ref of findFileRefs(text)) {
+    if (ref.index > ofs) {
+      parent.appendChild(htext(text.substring(ofs, ref.index)));
+    }
+    const link = html('span', { className: 'fileref' }, htext(ref.text));
+    link.dataset.path = ref.path;
+    link.dataset.line = String(ref.line);
+    if (ref.col) link.dataset.col = String(ref.col);
+    parent.appendChild(link);
+    ofs = ref.index + ref.text.length;
+  }
+  if (ofs < text.length) {
+    parent.appendChild(htext(text.substring(ofs)));
+  }
+}
+
 /**
  * Client side DOM of terminal emulation.
  *
@@ -36,18 +83,28 @@ const termKeyMap: { [key: string]: string } = {
  * This client receives screen updates and forwards keystrokes.
  */
 export class Term {
-  dom = html('pre', { tabIndex: 0, className: 'term' });
-  cursor = html('div', { className: 'term-cursor' });
+  dom = html('pre', {
+    tabIndex: 0,
+    className: 'term',
+    'aria-label': 'command output',
+  });
+  cursor = html('div', { className: 'term-cursor', 'aria-hidden': 'true' });
   cellSize = { width: 0, height: 0 };
+  /** The theme.spacingVersion that cellSize was measured at. */
+  private measuredSpacing = -1;
 
   delegates = {
     /** Sends a keyboard event to the terminal's subprocess. */
     key: (msg: proto.KeyEvent) => {},
+
+    /** Called on a Ctrl-click of a file reference in the output. */
+    openFile: (path: string, line: number, col?: number) => {},
   };
 
   constructor() {
     this.dom.onkeydown = (e) => this.onKeyDown(e);
     this.dom.onkeypress = (e) => this.onKeyPress(e);
+    this.dom.onclick = (e) => this.onClick(e);
     this.dom.appendChild(this.cursor);
     this.measure();
     // Create initial empty line, for height.
@@ -56,11 +113,16 @@ export class Term {
   }
 
   measure() {
-    document.body.appendChild(this.dom);
+    // Terms not yet shown are measured in the page, to pick up its styles.
+    const attached = this.dom.isConnected;
+    if (!attached) document.body.appendChild(this.dom);
+    this.cursor.style.width = '';
+    this.cursor.style.height = '';
     this.cursor.innerText = 'A';
     const { width, height } = getComputedStyle(this.cursor);
-    document.body.removeChild(this.dom);
+    if (!attached) document.body.removeChild(this.dom);
     this.cursor.innerText = '';
+    this.measuredSpacing = theme.spacingVersion;
     this.cursor.style.width = width;
     this.cursor.style.height = height;
     this.cellSize.width = Number(width!.replace('px', ''));
@@ -76,6 +138,8 @@ export class Term {
   }
 
   onUpdate(msg: proto.TermUpdate) {
+    // The line height or letter spacing may have changed since.
+    if (this.measuredSpacing !== theme.spacingVersion) this.measure();
     let childIdx = 0;
     let child = this.dom.children[1] as HTMLElement; // avoid this.cursor
     for (const rowSpans of msg.rows) {
@@ -99,7 +163,7 @@ export class Term {
           if (bright) hspan.classList.add(`bright`);
           if (fg > 0) hspan.classList.add(`fg${fg}`);
           if (bg > 0) hspan.classList.add(`bg${bg}`);
-          hspan.innerText = span.text;
+          appendLinkified(hspan, span.text);
           child.appendChild(hspan);
         }
       }
@@ -119,6 +183,16 @@ export class Term {
     this.cursor.style.display = show ? 'block' : 'none';
   }
 
+  onClick(ev: MouseEvent) {
+    if (!ev.ctrlKey) return;
+    const target = ev.target as HTMLElement;
+    const link = target.closest('.fileref') as HTMLElement | null;
+    if (!link) return;
+    const { path, line, col } = link.dataset;
+    this.delegates.openFile(path!, Number(line), col ? Number(col) : undefined);
+    ev.preventDefault();
+  }
+
   sendKeys(keys: string) {
     return this.delegates.key({ cell: 0, keys });
   }
@@ -0,0 +1,29 @@
+import { findFileRefs } from './term';
+import { expect } from 'chai';
+
+describe('term', () => {
+  describe('file refs', () => {
+    it('finds compiler references', () => {
+      const refs = findFileRefs('error at src/main.rs:12:5: oops');
+      expect(refs.length).equal(1);
+      expect(refs[0].index).equal('error at '.length);
+      expect(refs[0].text).equal('src/main.rs:12:5');
+      expect(refs[0].path).equal('src/main.rs');
+      expect(refs[0].line).equal(12);
+      expect(refs[0].col).equal(5);
+    });
+
+    it('allows a missing column', () => {
+      const refs = findFileRefs('foo.go:3 bar.ts:4');
+      expect(refs.map((r) => [r.path, r.line, r.col])).deep.equal([
+        ['foo.go', 3, undefined],
+        ['bar.ts', 4, undefined],
+      ]);
+    });
+
+    it('ignores urls and plain text', () => {
+      expect(findFileRefs('see http://example.com:8080/')).deep.equal([]);
+      expect(findFileRefs('at 12:30:00')).deep.equal([]);
+    });
+  });
+});
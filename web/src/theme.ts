@@ -0,0 +1,356 @@
+/**
+ * Color themes.  All colors in style.css come from CSS variables, which are
+ * overridden here to switch themes at runtime.
+ */
+
+/**
+ * A theme maps CSS variable names (without the leading "--") to values.
+ * Besides colors, themes may set the spacing variables from style.css, e.g.
+ * 'term-line-height'.
+ */
+export type Theme = { [name: string]: string };
+
+/** The spacing variables from style.css, which 'spacing' can override. */
+export const spacingVars = [
+  'pane-padding',
+  'prompt-padding',
+  'term-line-height',
+  'term-letter-spacing',
+];
+
+/** Spacing set by the user, which takes precedence over any theme's. */
+export const spacing = new Map<string, string>();
+
+/**
+ * Incremented whenever the spacing or font size may have changed, so terminals
+ * know to re-measure their character cells.
+ */
+export let spacingVersion = 0;
+
+/** Tells terminals to re-measure, e.g. after a font size change. */
+export function invalidateCellSize() {
+  spacingVersion++;
+}
+
+const tango = {
+  color1: '#2e3436',
+  color2: '#cc0000',
+  color3: '#4e9a06',
+  color4: '#c4a000',
+  color5: '#3465a4',
+  color6: '#75507b',
+  color7: '#06989a',
+  color8: '#d3d7cf',
+  bright1: '#555753',
+  bright2: '#ef2929',
+  bright3: '#8ae234',
+  bright4: '#fce94f',
+  bright5: '#729fcf',
+  bright6: '#ad7fa8',
+  bright7: '#34e2e2',
+  bright8: '#eeeeec',
+};
+
+export const themes = new Map<string, Theme>([
+  [
+    'light',
+    {
+      ...tango,
+      fg: 'black',
+      bg: 'white',
+      border: '#777',
+      shadow: '#aaa',
+      'highlight-bg': '#eee',
+      'selection-bg': '#b5d5ff',
+      cursor: 'rgba(255, 0, 0, 0.3)',
+      'error-border': '#f77',
+      'error-bg': '#fee',
+    },
+  ],
+  [
+    'dark',
+    {
+      ...tango,
+      fg: '#d3d7cf',
+      bg: '#1e1e1e',
+      border: '#555',
+      shadow: '#000',
+      'highlight-bg': '#2e2e2e',
+      'selection-bg': '#264f78',
+      cursor: 'rgba(255, 255, 255, 0.4)',
+      'error-border': '#a33',
+      'error-bg': '#401818',
+    },
+  ],
+]);
+
+/** The name of the currently applied theme. */
+export let current = 'light';
+
+/**
+ * Whether to follow the system dark/light preference, switching between the
+ * themes named in systemThemes.  Disabled by explicitly choosing a theme.
+ */
+export let auto = true;
+export const systemThemes = { light: 'light', dark: 'dark' };
+
+const darkQuery =
+  typeof window !== 'undefined'
+    ? window.matchMedia('(prefers-color-scheme: dark)')
+    : undefined;
+
+function applyTheme(name: string): boolean {
+  const theme = themes.get(name);
+  if (!theme) return false;
+  const style = document.documentElement.style;
+  for (const [key, val] of Object.entries(theme)) {
+    style.setProperty(`--${key}`, val);
+  }
+  for (const [key, val] of spacing) {
+    style.setProperty(`--${key}`, val);
+  }
+  invalidateCellSize();
+  current = name;
+  return true;
+}
+
+function applySystemTheme() {
+  if (!auto || !darkQuery) return;
+  applyTheme(darkQuery.matches ? systemThemes.dark : systemThemes.light);
+}
+
+/**
+ * Applies a theme by name, returning false if there is no such theme.
+ * The name 'auto' follows the system preference instead.
+ */
+export function setTheme(name: string): boolean {
+  if (name === 'auto') {
+    auto = true;
+    applySystemTheme();
+    return true;
+  }
+  if (!themes.has(name)) return false;
+  auto = false;
+  return applyTheme(name);
+}
+
+/**
+ * Sets a spacing variable, e.g. 'term-line-height' to '1.4', or with an empty
+ * value reverts it.  Returns false if there is no such variable.
+ */
+export function setSpacing(name: string, value: string): boolean {
+  if (!spacingVars.includes(name)) return false;
+  const style = document.documentElement.style;
+  if (value) {
+    spacing.set(name, value);
+    style.setProperty(`--${name}`, value);
+  } else {
+    spacing.delete(name);
+    style.setProperty(`--${name}`, themes.get(current)?.[name] ?? '');
+  }
+  invalidateCellSize();
+  return true;
+}
+
+/** Starts tracking changes to the system dark/light preference. */
+export function watchSystemTheme() {
+  darkQuery?.addEventListener('change', applySystemTheme);
+  applySystemTheme();
+}
+
+/** The names of the ANSI colors 0-7, as used in theme files and prompts. */
+// prettier-ignore
+export const ansiNames = [
+  'black', 'red', 'green', 'yellow', 'blue', 'magenta', 'cyan', 'white',
+];
+
+/** Returns the CSS variable for ANSI color n, 0-15. */
+function ansiVar(n: number): string {
+  return n < 8 ? `color${n + 1}` : `bright${n - 7}`;
+}
+
+/** Normalizes '0xRRGGBB' and 'RRGGBB' color spellings to '#RRGGBB'. */
+function hexColor(color: string): string {
+  color = color.trim().replace(/^['"]|['"]$/g, '');
+  return '#' + color.replace(/^(#|0x)/i, '');
+}
+
+function isDark(color: string): boolean {
+  const m = color.match(/^#([0-9a-f]{2})([0-9a-f]{2})([0-9a-f]{2})$/i);
+  if (!m) return false;
+  const [r, g, b] = m.slice(1).map((hex) => parseInt(hex, 16));
+  return 0.299 * r + 0.587 * g + 0.114 * b < 128;
+}
+
+/** Colors read from a theme file: ANSI colors 0-15, plus 'fg' and 'bg'. */
+type Palette = Map<number | 'fg' | 'bg', string>;
+
+/**
+ * Builds a theme from a palette.  Theme files don't cover smash's UI colors
+ * like borders, so those come from the built-in light or dark theme,
+ * whichever matches the background.
+ */
+function fromPalette(colors: Palette): Theme {
+  const bg = colors.get('bg');
+  const theme = { ...themes.get(bg && isDark(bg) ? 'dark' : 'light')! };
+  for (const [key, color] of colors) {
+    theme[typeof key === 'number' ? ansiVar(key) : key] = color;
+  }
+  return theme;
+}
+
+/** The base16 colors used for each ANSI color, per base16-shell. */
+// prettier-ignore
+const base16Ansi = [
+  '00', '08', '0B', '0A', '0D', '0E', '0C', '05',
+  '03', '08', '0B', '0A', '0D', '0E', '0C', '07',
+];
+
+/** Parses a base16 scheme, e.g. 'base00: "1d1f21"'. */
+function importBase16(text: string): Theme {
+  const base = new Map<string, string>();
+  for (const line of text.split('\n')) {
+    const m = line.match(/^\s*base(0[0-9A-F])\s*:\s*(\S+)/i);
+    if (m) base.set(m[1].toUpperCase(), hexColor(m[2]));
+  }
+  const colors: Palette = new Map();
+  base16Ansi.forEach((b, n) => {
+    const color = base.get(b);
+    if (color) colors.set(n, color);
+  });
+  if (base.has('05')) colors.set('fg', base.get('05')!);
+  if (base.has('00')) colors.set('bg', base.get('00')!);
+  return fromPalette(colors);
+}
+
+/** Parses a Ghostty theme, e.g. 'palette = 0=#1d1f21'. */
+function importGhostty(text: string): Theme {
+  const colors: Palette = new Map();
+  for (const line of text.split('\n')) {
+    const m = line.match(/^\s*([\w-]+)\s*=\s*(.*?)\s*$/);
+    if (!m) continue;
+    const [, key, val] = m;
+    if (key === 'palette') {
+      const p = val.match(/^(\d+)\s*=\s*(\S+)$/);
+      if (p && Number(p[1]) < 16) colors.set(Number(p[1]), hexColor(p[2]));
+    } else if (key === 'foreground') {
+      colors.set('fg', hexColor(val));
+    } else if (key === 'background') {
+      colors.set('bg', hexColor(val));
+    }
+  }
+  return fromPalette(colors);
+}
+
+/** Parses the [colors.*] tables of an Alacritty TOML config. */
+function importAlacritty(text: string): Theme {
+  const colors: Palette = new Map();
+  let section = '';
+  for (const line of text.split('\n')) {
+    const header = line.match(/^\s*\[(.*)\]\s*$/);
+    if (header) {
+      section = header[1].trim();
+      continue;
+    }
+    const m = line.match(/^\s*(\w+)\s*=\s*(\S+)/);
+    if (!m) continue;
+    const [, key, val] = m;
+    const n = ansiNames.indexOf(key);
+    if (section === 'colors.primary' && key === 'foreground') {
+      colors.set('fg', hexColor(val));
+    } else if (section === 'colors.primary' && key === 'background') {
+      colors.set('bg', hexColor(val));
+    } else if (section === 'colors.normal' && n >= 0) {
+      colors.set(n, hexColor(val));
+    } else if (section === 'colors.bright' && n >= 0) {
+      colors.set(n + 8, hexColor(val));
+    }
+  }
+  return fromPalette(colors);
+}
+
+/** Parses an iTerm2 .itermcolors property list. */
+function importITerm(text: string): Theme {
+  const colors: Palette = new Map();
+  const dicts = /<key>([^<]*)<\/key>\s*<dict>([\s\S]*?)<\/dict>/g;
+  let m: RegExpExecArray | null;
+  while ((m = dicts.exec(text))) {
+    const [, name, dict] = m;
+    const component = (c: string) => {
+      const m = dict.match(
+        new RegExp(`<key>${c} Component</key>\\s*<real>([^<]*)</real>`)
+      );
+      const val = Math.round(Number(m?.[1] ?? 0) * 255);
+      return Math.max(0, Math.min(255, val)).toString(16).padStart(2, '0');
+    };
+    const color =
+      '#' + component('Red') + component('Green') + component('Blue');
+    const ansi = name.match(/^Ansi (\d+) Color$/);
+    if (ansi && Number(ansi[1]) < 16) colors.set(Number(ansi[1]), color);
+    else if (name === 'Foreground Color') colors.set('fg', color);
+    else if (name === 'Background Color') colors.set('bg', color);
+  }
+  return fromPalette(colors);
+}
+
+/**
+ * Converts an X color spec to CSS.  Besides names and #RRGGBB, which CSS
+ * understands, X allows rgb:R/G/B with 1-4 hex digits per component.
+ */
+function xColor(color: string): string {
+  color = color.replace(/^['"]|['"]$/g, '');
+  const m = color.match(
+    /^rgb:([0-9a-f]{1,4})\/([0-9a-f]{1,4})\/([0-9a-f]{1,4})$/i
+  );
+  if (!m) return color;
+  const hex = m.slice(1).map((c) => {
+    const val = Math.round((parseInt(c, 16) / (16 ** c.length - 1)) * 255);
+    return val.toString(16).padStart(2, '0');
+  });
+  return '#' + hex.join('');
+}
+
+const xresourceColor =
+  /^(?:[\w-]*[.*])*(color(\d+)|foreground|background)\s*:\s*(\S+)/;
+
+/**
+ * Parses the colors from X resources, as in ~/.Xresources: '*.color0: #000'
+ * and the like, for any program (e.g. 'URxvt.color0').  Simple #define
+ * macros, often used to name colors, are expanded.
+ */
+function importXresources(text: string): Theme {
+  const colors: Palette = new Map();
+  const defines = new Map<string, string>();
+  for (const line of text.split('\n')) {
+    const define = line.match(/^\s*#define\s+(\S+)\s+(\S+)/);
+    if (define) {
+      defines.set(define[1], define[2]);
+      continue;
+    }
+    const m = line.trim().match(xresourceColor);
+    if (!m) continue;
+    const [, key, n, val] = m;
+    const color = xColor(defines.get(val) ?? val);
+    if (key === 'foreground') colors.set('fg', color);
+    else if (key === 'background') colors.set('bg', color);
+    else if (Number(n) < 16) colors.set(Number(n), color);
+  }
+  return fromPalette(colors);
+}
+
+/**
+ * Converts a theme file from another terminal into a theme, detecting its
+ * format from the contents.  Supports base16 schemes, Ghostty themes,
+ * Alacritty TOML, iTerm2 .itermcolors files, and X resources.  Returns
+ * undefined for unrecognized files.
+ */
+export function importTheme(text: string): Theme | undefined {
+  if (/<plist/.test(text)) return importITerm(text);
+  if (/^\s*\[colors\./m.test(text)) return importAlacritty(text);
+  if (/^\s*palette\s*=/m.test(text)) return importGhostty(text);
+  if (/^\s*base0[0-9A-F]\s*:/im.test(text)) return importBase16(text);
+  if (text.split('\n').some((line) => xresourceColor.test(line.trim()))) {
+    return importXresources(text);
+  }
+  return undefined;
+}
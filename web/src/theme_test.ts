@@ -0,0 +1,84 @@
+import { importTheme } from './theme';
+import { expect } from 'chai';
+
+describe('theme import', () => {
+  it('imports base16 schemes', () => {
+    const theme = importTheme(
+      ['scheme: "Test"', 'base00: "1d1f21"', 'base05: "c5c8c6"'].join('\n')
+    );
+    expect(theme).deep.include({
+      bg: '#1d1f21',
+      fg: '#c5c8c6',
+      color1: '#1d1f21',
+      color8: '#c5c8c6',
+    });
+    // UI colors come from the built-in dark theme.
+    expect(theme?.border).equal('#555');
+  });
+
+  it('imports Ghostty themes', () => {
+    const theme = importTheme(
+      ['palette = 1=#ff0000', 'palette = 9=00ff00', 'background = fff'].join(
+        '\n'
+      )
+    );
+    expect(theme).deep.include({
+      color2: '#ff0000',
+      bright2: '#00ff00',
+      bg: '#fff',
+    });
+  });
+
+  it('imports Alacritty TOML', () => {
+    const theme = importTheme(
+      [
+        '[colors.primary]',
+        "background = '#282828'",
+        '[colors.normal]',
+        "red = '0xcc241d'",
+        '[colors.bright]',
+        "white = '#fbf1c7'",
+      ].join('\n')
+    );
+    expect(theme).deep.include({
+      bg: '#282828',
+      color2: '#cc241d',
+      bright8: '#fbf1c7',
+    });
+  });
+
+  it('imports iTerm2 colors', () => {
+    const theme = importTheme(`<plist><dict>
+      <key>Ansi 4 Color</key>
+      <dict>
+        <key>Blue Component</key><real>1</real>
+        <key>Green Component</key><real>0.5</real>
+        <key>Red Component</key><real>0</real>
+      </dict>
+    </dict></plist>`);
+    expect(theme?.color5).equal('#0080ff');
+  });
+
+  it('imports X resources', () => {
+    const theme = importTheme(
+      [
+        '! comment',
+        '#define red rgb:cc/00/00',
+        '*.foreground: #c5c8c6',
+        'URxvt.background: rgb:1d1d/1f1f/2121',
+        '*color1: red',
+        'XTerm*vt100.color9: #ff0000',
+      ].join('\n')
+    );
+    expect(theme).deep.include({
+      fg: '#c5c8c6',
+      bg: '#1d1f21',
+      color2: '#cc0000',
+      bright2: '#ff0000',
+    });
+  });
+
+  it('rejects unknown formats', () => {
+    expect(importTheme('hello')).equal(undefined);
+  });
+});